@@ -0,0 +1,251 @@
+//!
+//! A persistent cache of responses, so what was seen once
+//! can be seen again without (or with less of) the network.
+//!
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{HeaderMap, CACHE_CONTROL},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+///
+/// What the TTL of anything outside the API (like pictures) is looked up by.
+///
+pub const MEDIA: &str = "media";
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub dir: PathBuf,
+
+    ///
+    /// Never touch the network: serve whatever is cached, however old.
+    ///
+    pub offline: bool,
+
+    ///
+    /// How long a response stays fresh, by endpoint (`models`, `images`, ...) or [MEDIA].
+    ///
+    pub ttl: HashMap<String, Duration>,
+
+    ///
+    /// For endpoints without their own TTL
+    ///
+    pub default_ttl: Duration,
+}
+
+///
+/// A cached response.
+///
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub body: Vec<u8>,
+    pub meta: EntryMeta,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EntryMeta {
+    ///
+    /// Seconds since the epoch
+    ///
+    pub fetched_at: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let ttl = [
+            ("models", HOUR),
+            ("model-versions", HOUR),
+            ("images", HOUR / 4),
+            ("creators", HOUR * 24),
+            ("tags", HOUR * 24),
+            (MEDIA, HOUR * 24 * 30),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        Self {
+            dir: dir.into(),
+            offline: false,
+            ttl,
+            default_ttl: HOUR,
+        }
+    }
+
+    ///
+    /// The user's cache directory (`$XDG_CACHE_HOME` or `~/.cache`), if there is one.
+    ///
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|dir| dir.join("civitai-tui"))
+    }
+
+    ///
+    /// The TTL for `url`, by the endpoint it belongs to.
+    ///
+    pub fn ttl_for(&self, base: &Url, url: &Url) -> Duration {
+        let endpoint = match url.path().strip_prefix(base.path()) {
+            Some(rest) if url.origin() == base.origin() => rest.split('/').next().unwrap_or(""),
+            _ => MEDIA,
+        };
+
+        self.ttl.get(endpoint).copied().unwrap_or(self.default_ttl)
+    }
+
+    ///
+    /// What the response to `url` is cached under: a hash of the whole url,
+    /// and of the API key it was asked with, so users never see each other's responses.
+    ///
+    pub fn key(url: &Url, api_key: Option<&str>) -> String {
+        let mut hasher = Sha256::new();
+
+        hasher.update(url.as_str());
+        if let Some(api_key) = api_key {
+            hasher.update([0]);
+            hasher.update(api_key);
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub(crate) fn load(&self, key: &str) -> Option<Entry> {
+        let body = fs::read(self.dir.join(key)).ok()?;
+        let meta = fs::read(self.meta_path(key))
+            .ok()
+            .and_then(|raw| serde_json::from_slice(&raw).ok())
+            .unwrap_or_default();
+
+        Some(Entry { body, meta })
+    }
+
+    ///
+    /// Stores a fresh response, keeping its validators for revalidation later.
+    /// Responses marked `Cache-Control: no-store` are not kept (and replace nothing).
+    ///
+    pub(crate) fn store(&self, key: &str, body: &[u8], headers: &HeaderMap) -> std::io::Result<()> {
+        if no_store(headers) {
+            let _ = fs::remove_file(self.dir.join(key));
+            let _ = fs::remove_file(self.meta_path(key));
+            return Ok(());
+        }
+
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        let meta = EntryMeta {
+            fetched_at: now(),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(key), body)?;
+        self.write_meta(key, &meta)
+    }
+
+    ///
+    /// The server said the cached response is still good.
+    ///
+    pub(crate) fn refresh(&self, key: &str, mut meta: EntryMeta) -> std::io::Result<()> {
+        meta.fetched_at = now();
+        self.write_meta(key, &meta)
+    }
+
+    fn write_meta(&self, key: &str, meta: &EntryMeta) -> std::io::Result<()> {
+        let raw = serde_json::to_vec(meta).map_err(std::io::Error::other)?;
+        fs::write(self.meta_path(key), raw)
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.meta"))
+    }
+}
+
+impl EntryMeta {
+    pub(crate) fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+}
+
+fn no_store(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::Url;
+
+    use super::{Cache, MEDIA};
+
+    #[test]
+    fn test_ttl_for() -> anyhow::Result<()> {
+        let base = Url::parse("https://civitai.com/api/v1/")?;
+        let mut cache = Cache::new("/tmp/unused");
+        cache.ttl.insert("tags".to_string(), Duration::from_secs(5));
+        cache.ttl.insert(MEDIA.to_string(), Duration::from_secs(7));
+
+        let ttl = |url: &str| cache.ttl_for(&base, &Url::parse(url).unwrap());
+
+        assert_eq!(ttl("https://civitai.com/api/v1/tags?limit=3"), Duration::from_secs(5));
+        assert_eq!(ttl("https://image.civitai.com/abc/1.jpeg"), Duration::from_secs(7));
+        assert_eq!(ttl("https://civitai.com/api/v1/unheard-of"), cache.default_ttl);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key() -> anyhow::Result<()> {
+        let url = |url: &str| Url::parse(url).unwrap();
+        let key = |u: &str, api_key| Cache::key(&url(u), api_key);
+
+        // Queries which only differ in characters a file name could not have.
+        assert_ne!(
+            key("https://civitai.com/api/v1/models?query=a/b", None),
+            key("https://civitai.com/api/v1/models?query=a_b", None)
+        );
+        assert_ne!(
+            key("https://civitai.com/api/v1/models", None),
+            key("https://civitai.com/api/v1/models", Some("secret"))
+        );
+        assert_eq!(
+            key("https://civitai.com/api/v1/models", Some("secret")),
+            key("https://civitai.com/api/v1/models", Some("secret"))
+        );
+
+        Ok(())
+    }
+}
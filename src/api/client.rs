@@ -0,0 +1,492 @@
+//!
+//! The HTTP client every endpoint goes through.
+//!
+
+use std::sync::Arc;
+
+use reqwest::{
+    header::{self, HeaderValue},
+    Client, IntoUrl, Request, RequestBuilder, Response, StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+
+use super::{
+    cache::Cache,
+    error::{ApiError, Result},
+    recorder::{self, Recorder},
+    throttle::{self, Limiter, RateLimit, RetryPolicy},
+};
+
+///
+/// The public Civitai API.
+///
+pub const DEFAULT_BASE_URL: &str = "https://civitai.com/api/v1/";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    ///
+    /// The url endpoint paths are resolved against,
+    /// e.g. a local mock or a mirror of the API.
+    ///
+    pub base_url: String,
+
+    ///
+    /// API token sent as a bearer token with every request,
+    /// needed for gated content.
+    ///
+    pub api_key: Option<String>,
+
+    ///
+    /// Record responses to (or replay them from) disk.
+    ///
+    pub recorder: Option<Recorder>,
+
+    ///
+    /// Shared by every request the client sends, `None` for no limit.
+    ///
+    pub rate_limit: Option<RateLimit>,
+
+    pub retry: RetryPolicy,
+
+    ///
+    /// Keep responses on disk, and reuse them while fresh.
+    ///
+    pub cache: Option<Cache>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: None,
+            recorder: None,
+            rate_limit: Some(RateLimit::default()),
+            retry: RetryPolicy::default(),
+            cache: None,
+        }
+    }
+}
+
+impl Config {
+    ///
+    /// Reads `CIVITAI_BASE_URL`, `CIVITAI_API_KEY`,
+    /// `CIVITAI_RECORD_DIR` or `CIVITAI_REPLAY_DIR`,
+    /// `CIVITAI_RATE_LIMIT` (requests per second, 0 for no limit), `CIVITAI_MAX_RETRIES`,
+    /// `CIVITAI_CACHE_DIR` (empty for no cache) and `CIVITAI_OFFLINE`,
+    /// falling back to the defaults for anything unset.
+    ///
+    /// Unlike [Config::default], this caches responses in the user's cache directory.
+    ///
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let recorder = std::env::var_os("CIVITAI_REPLAY_DIR")
+            .map(Recorder::replay)
+            .or_else(|| std::env::var_os("CIVITAI_RECORD_DIR").map(Recorder::record));
+
+        let rate_limit = match env_parse::<f64>("CIVITAI_RATE_LIMIT") {
+            Some(per_second) if per_second <= 0.0 => None,
+            Some(per_second) => Some(RateLimit {
+                per_second,
+                ..Default::default()
+            }),
+            None => default.rate_limit,
+        };
+
+        let retry = RetryPolicy {
+            max_retries: env_parse("CIVITAI_MAX_RETRIES").unwrap_or(default.retry.max_retries),
+            ..default.retry
+        };
+
+        let cache = match std::env::var_os("CIVITAI_CACHE_DIR") {
+            Some(dir) if dir.is_empty() => None,
+            Some(dir) => Some(Cache::new(dir)),
+            None => Cache::default_dir().map(Cache::new),
+        }
+        .map(|cache| Cache {
+            offline: std::env::var("CIVITAI_OFFLINE").is_ok_and(|v| !matches!(v.as_str(), "" | "0")),
+            ..cache
+        });
+
+        Self {
+            base_url: std::env::var("CIVITAI_BASE_URL").unwrap_or(default.base_url),
+            api_key: std::env::var("CIVITAI_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+            recorder,
+            rate_limit,
+            retry,
+            cache,
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok()?.trim().parse().ok()
+}
+
+struct Inner {
+    http: Client,
+    base_url: Url,
+    api_key: Option<String>,
+    recorder: Option<Recorder>,
+    limiter: Option<Limiter>,
+    retry: RetryPolicy,
+    cache: Option<Cache>,
+}
+
+///
+/// A cheaply clonable handle to one pooled connection,
+/// a base url, and the (optional) API token.
+///
+#[derive(Clone)]
+pub struct CivitaiClient(Arc<Inner>);
+
+impl std::fmt::Debug for CivitaiClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CivitaiClient")
+            .field("base_url", &self.0.base_url.as_str())
+            .field("api_key", &self.0.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl CivitaiClient {
+    pub fn new(config: Config) -> Result<Self> {
+        let mut base_url = config.base_url;
+
+        // Without the trailing slash, `Url::join` would replace the last segment.
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        Ok(Self(Arc::new(Inner {
+            http: Client::builder().build()?,
+            base_url: Url::parse(&base_url).map_err(|_| ApiError::InvalidUrl(base_url))?,
+            api_key: config.api_key,
+            recorder: config.recorder,
+            limiter: config.rate_limit.map(Limiter::new),
+            retry: config.retry,
+            cache: config.cache,
+        })))
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::new(Config::from_env())
+    }
+
+    ///
+    /// Whether the client only serves cached responses.
+    ///
+    pub fn is_offline(&self) -> bool {
+        self.0.cache.as_ref().is_some_and(|c| c.offline)
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.0.base_url
+    }
+
+    ///
+    /// Resolves an endpoint path (like `models`) against the base url.
+    ///
+    pub fn endpoint(&self, path: &str) -> Result<Url> {
+        self.0
+            .base_url
+            .join(path.trim_start_matches('/'))
+            .map_err(|_| ApiError::InvalidUrl(path.to_string()))
+    }
+
+    ///
+    /// Starts a GET request on the shared connection pool,
+    /// with the API token attached (if any).
+    ///
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        let req = self.0.http.get(url);
+
+        match self.0.api_key {
+            Some(ref key) => req.bearer_auth(key),
+            None => req,
+        }
+    }
+
+    pub async fn send_request<T: DeserializeOwned>(
+        &self,
+        url: impl IntoUrl,
+        params: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<T> {
+        let req = self
+            .get(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .query(&params.into_iter().collect::<Vec<_>>());
+
+        let (url, body) = self.fetch(req, true).await?;
+        let txt = String::from_utf8_lossy(&body);
+
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&txt))
+            .map_err(|err| ApiError::decode(url, &txt, err))
+    }
+
+    ///
+    /// Downloads the raw body at `url` (used for images).
+    ///
+    pub async fn fetch_bytes(&self, url: impl IntoUrl) -> Result<Vec<u8>> {
+        self.fetch(self.get(url), false)
+            .await
+            .map(|(_, body)| body)
+    }
+
+    ///
+    /// Sends `req` (or replays it), failing on any non-success status.
+    /// Waits its turn with the rate limiter, and retries transient failures.
+    ///
+    /// Fresh cached responses are served without asking the server,
+    /// stale ones are revalidated with their `ETag` or `Last-Modified`.
+    ///
+    async fn fetch(&self, req: RequestBuilder, json: bool) -> Result<(Url, Vec<u8>)> {
+        let mut req = req.build()?;
+        let url = req.url().clone();
+        let key = Recorder::key(&self.0.base_url, &url, json);
+        let cache_key = Cache::key(&url, self.0.api_key.as_deref());
+
+        if let Some(rec @ Recorder { mode: recorder::Mode::Replay, .. }) = &self.0.recorder {
+            return rec.load(&key).map(|body| (url, body));
+        }
+
+        let mut cached = None;
+        if let Some(ref cache) = self.0.cache {
+            match cache.load(&cache_key) {
+                Some(entry)
+                    if cache.offline || entry.meta.age() < cache.ttl_for(&self.0.base_url, &url) =>
+                {
+                    return Ok((url, entry.body));
+                }
+                None if cache.offline => return Err(ApiError::Offline(url.to_string())),
+                entry => cached = entry,
+            }
+        }
+
+        if let Some(ref entry) = cached {
+            let validators = [
+                (header::IF_NONE_MATCH, &entry.meta.etag),
+                (header::IF_MODIFIED_SINCE, &entry.meta.last_modified),
+            ];
+
+            for (name, value) in validators {
+                if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                    req.headers_mut().insert(name, value);
+                }
+            }
+        }
+
+        let res = self.execute(req).await?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = res.bytes().await?;
+
+        if let (StatusCode::NOT_MODIFIED, Some(cache), Some(entry)) =
+            (status, &self.0.cache, cached)
+        {
+            // Only the freshness is bookkeeping, the body is still good without it.
+            let _ = cache.refresh(&cache_key, entry.meta);
+            return Ok((url, entry.body));
+        }
+
+        if !status.is_success() {
+            return Err(ApiError::status(url, status, &String::from_utf8_lossy(&body)));
+        }
+
+        if let Some(ref rec) = self.0.recorder {
+            let secrets = self.0.api_key.as_deref().into_iter().collect::<Vec<_>>();
+            rec.save(&key, &body, &secrets)?;
+        }
+
+        if let Some(ref cache) = self.0.cache {
+            // A cache which can not be written to should not fail the request.
+            let _ = cache.store(&cache_key, &body, &headers);
+        }
+
+        Ok((url, body.to_vec()))
+    }
+
+    ///
+    /// Sends `req` once the rate limiter allows, retrying transient failures.
+    /// The response is returned whatever its status, with the body unread.
+    ///
+    pub(crate) async fn execute(&self, req: Request) -> Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            if let Some(ref limiter) = self.0.limiter {
+                limiter.acquire().await;
+            }
+
+            // Only GET requests are sent, and having no body they always clone.
+            let sent = req.try_clone().expect("Clonable request!");
+            let res = self.0.http.execute(sent).await;
+
+            let retry = &self.0.retry;
+            let wait = match res {
+                Ok(ref res) if throttle::is_transient(res.status()) => {
+                    retry.delay(attempt, throttle::retry_after(res))
+                }
+                Err(ref err) if err.is_timeout() || err.is_connect() => retry.delay(attempt, None),
+                _ => None,
+            };
+
+            match wait {
+                Some(wait) => {
+                    attempt += 1;
+                    tokio::time::sleep(wait).await;
+                }
+                None => return Ok(res?),
+            }
+        }
+    }
+}
+
+impl Default for CivitaiClient {
+    fn default() -> Self {
+        Self::new(Config::default()).expect("Valid default config!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::api::{cache::Cache, fixtures::serve, throttle::RetryPolicy, ApiError};
+
+    use super::{CivitaiClient, Config};
+
+    #[tokio::test]
+    async fn test_retry() -> anyhow::Result<()> {
+        let (base_url, requests) = serve(&[
+            "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 429 Too Many Requests\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 11\r\n\r\n{\"ok\":true}",
+        ])
+        .await?;
+
+        let client = CivitaiClient::new(Config {
+            base_url: base_url.clone(),
+            retry: RetryPolicy {
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        let body: serde_json::Value = client.send_request(client.endpoint("models")?, []).await?;
+        assert_eq!(body["ok"], true);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        // Without retries, the first failure is final.
+        let (base_url, requests) = serve(&[
+            "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ])
+        .await?;
+
+        let client = CivitaiClient::new(Config {
+            base_url,
+            retry: RetryPolicy::none(),
+            ..Default::default()
+        })?;
+
+        let err = client
+            .send_request::<serde_json::Value>(client.endpoint("models")?, [])
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache() -> anyhow::Result<()> {
+        let (base_url, requests) = serve(&[
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nConnection: close\r\nContent-Length: 11\r\n\r\n{\"ok\":true}",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nCache-Control: private, no-store\r\nConnection: close\r\nContent-Length: 12\r\n\r\n{\"ok\":false}",
+        ])
+        .await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-cache-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+
+        let client = |cache: Cache| {
+            CivitaiClient::new(Config {
+                base_url: base_url.clone(),
+                cache: Some(cache),
+                ..Default::default()
+            })
+        };
+        let with_key = |cache: Cache| {
+            CivitaiClient::new(Config {
+                base_url: base_url.clone(),
+                api_key: Some("secret".to_string()),
+                cache: Some(cache),
+                ..Default::default()
+            })
+        };
+
+        let get = |client: CivitaiClient, path: &'static str| async move {
+            let url = client.endpoint(path)?;
+            client.send_request::<serde_json::Value>(url, []).await
+        };
+
+        // Fetched once, then served while fresh.
+        let fresh = client(cache.clone())?;
+        assert_eq!(get(fresh.clone(), "models").await?["ok"], true);
+        assert_eq!(get(fresh, "models").await?["ok"], true);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Once stale, asked after with the ETag.
+        let mut stale = cache.clone();
+        stale.default_ttl = Duration::ZERO;
+        stale.ttl.clear();
+        assert_eq!(get(client(stale)?, "models").await?["ok"], true);
+        assert!(requests.lock().unwrap()[1].contains("if-none-match: \"v1\""));
+
+        // Another API key does not get the same responses, and `no-store` ones are not kept.
+        assert_eq!(get(with_key(cache.clone())?, "models").await?["ok"], false);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        // Offline, only what is cached.
+        let offline = client(Cache {
+            offline: true,
+            ..cache.clone()
+        })?;
+        assert_eq!(get(offline.clone(), "models").await?["ok"], true);
+        assert!(matches!(get(offline, "tags").await, Err(ApiError::Offline(_))));
+
+        let offline = with_key(Cache {
+            offline: true,
+            ..cache
+        })?;
+        assert!(matches!(get(offline, "models").await, Err(ApiError::Offline(_))));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoint_join() -> anyhow::Result<()> {
+        let client = CivitaiClient::new(Config {
+            base_url: "http://localhost:8080/api/v1".to_string(),
+            ..Default::default()
+        })?;
+
+        assert_eq!(
+            client.endpoint("models")?.as_str(),
+            "http://localhost:8080/api/v1/models"
+        );
+        assert_eq!(
+            client.endpoint("/creators")?.as_str(),
+            "http://localhost:8080/api/v1/creators"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,565 @@
+//!
+//! Downloading model files to disk.
+//!
+//! Files are streamed into a `.part` file next to their destination,
+//! which a later download picks up from with a range request,
+//! and only renamed into place once complete (and matching its hashes).
+//! Sidecars (see [Sidecar]) are written next to it after that, if asked for.
+//!
+
+use std::{
+    cmp::Ordering,
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use futures::StreamExt;
+use reqwest::{header, Response, StatusCode};
+use tokio::{
+    fs,
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use super::{
+    safety::{Policy, Unsafe},
+    sidecar::{Sidecar, SidecarError},
+    types::model::{File, Hashes},
+    verify::{self, Mismatch},
+    ApiError, CivitaiClient,
+};
+
+const PART_SUFFIX: &str = ".part";
+
+///
+/// Where files which fail verification are moved, next to their destination.
+///
+pub const QUARANTINE_DIR: &str = ".quarantine";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+
+    ///
+    /// The file is not downloaded, going by its scans and the [Policy].
+    ///
+    #[error("Refusing to download: {0}")]
+    Unsafe(#[from] Unsafe),
+
+    #[error("Writing {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: io::Error,
+    },
+
+    ///
+    /// The server stopped short (the `.part` file is kept to resume from).
+    ///
+    #[error("Expected {expected} bytes, but got {got}")]
+    Incomplete { expected: u64, got: u64 },
+
+    ///
+    /// The server sent a range starting after the one asked for.
+    ///
+    #[error("Asked for bytes from {requested}, but got them from {got}")]
+    Range { requested: u64, got: u64 },
+
+    ///
+    /// The file is not what the API says it is, and was moved aside.
+    ///
+    #[error("Downloaded file does not match: {mismatch} (moved to {})", quarantined.display())]
+    HashMismatch {
+        mismatch: Mismatch,
+        quarantined: PathBuf,
+    },
+
+    ///
+    /// The file was downloaded, but (some of) its sidecars could not be written.
+    ///
+    #[error("Writing sidecars: {0}")]
+    Sidecar(#[from] SidecarError),
+}
+
+type Result<T> = std::result::Result<T, DownloadError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    ///
+    /// Bytes on disk so far, including those of a resumed `.part` file
+    ///
+    pub downloaded: u64,
+
+    ///
+    /// The size of the whole file, if the server said
+    ///
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub url: String,
+
+    ///
+    /// Where the finished file ends up
+    ///
+    pub dest: PathBuf,
+
+    ///
+    /// What the finished file is checked against, before it is moved into place
+    ///
+    pub hashes: Option<Hashes>,
+
+    ///
+    /// What to write sidecars from, once the file is in place
+    ///
+    pub sidecar: Option<Sidecar>,
+}
+
+impl Download {
+    pub fn new(url: impl ToString, dest: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.to_string(),
+            dest: dest.into(),
+            hashes: None,
+            sidecar: None,
+        }
+    }
+
+    pub fn with_sidecar(self, sidecar: Sidecar) -> Self {
+        Self {
+            sidecar: Some(sidecar),
+            ..self
+        }
+    }
+
+    ///
+    /// Downloads `file` into `dir`, under its own name,
+    /// if it is safe enough for `policy`.
+    ///
+    pub fn for_file(file: &File, dir: impl AsRef<Path>, policy: &Policy) -> Result<Self> {
+        policy.check(file)?;
+
+        // The name comes from the server, so it must not point anywhere else.
+        let name = Path::new(&file.name)
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(file.id.to_string()));
+
+        Ok(Self {
+            hashes: Some(file.hashes.clone()),
+            ..Self::new(&file.download_url, dir.as_ref().join(name))
+        })
+    }
+
+    pub fn part_path(&self) -> PathBuf {
+        let mut part = self.dest.clone().into_os_string();
+        part.push(PART_SUFFIX);
+        part.into()
+    }
+
+    ///
+    /// Downloads the file (with the client's API token, for gated files),
+    /// carrying on from a previous `.part` file if there is one.
+    ///
+    /// Reports which do not fit in `progress` are dropped,
+    /// so a slow reader never holds up the download.
+    /// Gives the path of the finished file.
+    ///
+    pub async fn run(
+        &self,
+        client: &CivitaiClient,
+        progress: Option<mpsc::Sender<Progress>>,
+    ) -> Result<PathBuf> {
+        let path = self.download(client, progress).await?;
+
+        if let Some(sidecar) = &self.sidecar {
+            sidecar.write(client, &path).await?;
+        }
+
+        Ok(path)
+    }
+
+    async fn download(
+        &self,
+        client: &CivitaiClient,
+        progress: Option<mpsc::Sender<Progress>>,
+    ) -> Result<PathBuf> {
+        if client.is_offline() {
+            return Err(ApiError::Offline(self.url.clone()).into());
+        }
+
+        let part = self.part_path();
+
+        if let Some(dir) = self.dest.parent() {
+            fs::create_dir_all(dir).await.map_err(io_error(dir))?;
+        }
+
+        let mut have = fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0);
+
+        let (res, total) = loop {
+            let mut req = client.get(&self.url);
+            if have > 0 {
+                req = req.header(header::RANGE, format!("bytes={have}-"));
+            }
+
+            let res = client.execute(req.build().map_err(ApiError::from)?).await?;
+            if res.status() != StatusCode::PARTIAL_CONTENT {
+                break (res, None);
+            }
+
+            let (start, total) = content_range(&res).unwrap_or((have, None));
+            match start.cmp(&have) {
+                // Appending would leave a gap, so start over without a range.
+                Ordering::Greater if have > 0 => have = 0,
+                Ordering::Greater => {
+                    return Err(DownloadError::Range {
+                        requested: have,
+                        got: start,
+                    })
+                }
+
+                // What the server sends again replaces the end of the part.
+                Ordering::Less => {
+                    truncate(&part, start).await?;
+                    have = start;
+                    break (res, total);
+                }
+                Ordering::Equal => break (res, total),
+            }
+        };
+
+        let total = match res.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                total.or_else(|| res.content_length().map(|len| len + have))
+            }
+
+            // The part is whole already, it just was not renamed.
+            StatusCode::RANGE_NOT_SATISFIABLE if have > 0 => {
+                let total = content_range(&res).and_then(|(_, total)| total);
+                return self.finish(have, total, &progress).await;
+            }
+
+            // Ranges are not supported, start over.
+            status if status.is_success() => {
+                have = 0;
+                res.content_length()
+            }
+
+            status => {
+                let url = res.url().clone();
+                let body = res.text().await.unwrap_or_default();
+                return Err(ApiError::status(url, status, &body).into());
+            }
+        };
+
+        let mut out = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&part)
+            .await
+            .map_err(io_error(&part))?;
+
+        out.set_len(have).await.map_err(io_error(&part))?;
+        out.seek(SeekFrom::Start(have)).await.map_err(io_error(&part))?;
+
+        report(&progress, have, total);
+
+        let mut body = res.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(ApiError::from)?;
+            out.write_all(&chunk).await.map_err(io_error(&part))?;
+
+            have += chunk.len() as u64;
+            report(&progress, have, total);
+        }
+
+        out.flush().await.map_err(io_error(&part))?;
+        out.sync_all().await.map_err(io_error(&part))?;
+
+        self.finish(have, total, &progress).await
+    }
+
+    ///
+    /// Checks the complete `.part` file, and moves it into place.
+    ///
+    async fn finish(
+        &self,
+        have: u64,
+        total: Option<u64>,
+        progress: &Option<mpsc::Sender<Progress>>,
+    ) -> Result<PathBuf> {
+        if let Some(expected) = total.filter(|&t| t != have) {
+            return Err(DownloadError::Incomplete {
+                expected,
+                got: have,
+            });
+        }
+
+        self.verify().await?;
+
+        fs::rename(self.part_path(), &self.dest)
+            .await
+            .map_err(io_error(&self.dest))?;
+
+        report(progress, have, Some(have));
+        Ok(self.dest.clone())
+    }
+}
+
+impl Download {
+    ///
+    /// Quarantines the `.part` file if any of the expected hashes differ.
+    ///
+    async fn verify(&self) -> Result<()> {
+        let Some(expected) = self.hashes.clone() else {
+            return Ok(());
+        };
+
+        let part = self.part_path();
+
+        let mismatches = {
+            let part = part.clone();
+            tokio::task::spawn_blocking(move || verify::verify(&part, &expected))
+        }
+        .await
+        .expect("Hashing panicked!")
+        .map_err(io_error(&part))?;
+
+        let Some(mismatch) = mismatches.into_iter().next() else {
+            return Ok(());
+        };
+
+        let dir = self
+            .dest
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(QUARANTINE_DIR);
+        fs::create_dir_all(&dir).await.map_err(io_error(&dir))?;
+
+        let name = self.dest.file_name().unwrap_or(part.as_os_str());
+        let quarantined = dir.join(name);
+        fs::rename(&part, &quarantined)
+            .await
+            .map_err(io_error(&quarantined))?;
+
+        Err(DownloadError::HashMismatch {
+            mismatch,
+            quarantined,
+        })
+    }
+}
+
+///
+/// The start and (if known) total size from a `Content-Range: bytes 5-9/10`.
+///
+fn content_range(res: &Response) -> Option<(u64, Option<u64>)> {
+    let value = res.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+
+    let start = match range.split_once('-') {
+        Some((start, _)) => start.trim().parse().ok()?,
+        // `bytes */10`, as sent with a 416
+        None => 0,
+    };
+
+    Some((start, total.trim().parse().ok()))
+}
+
+fn report(progress: &Option<mpsc::Sender<Progress>>, downloaded: u64, total: Option<u64>) {
+    if let Some(tx) = progress {
+        // Nobody listening, or nobody keeping up, is fine: later reports follow.
+        let _ = tx.try_send(Progress { downloaded, total });
+    }
+}
+
+async fn truncate(path: &Path, len: u64) -> Result<()> {
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await
+        .map_err(io_error(path))?;
+
+    file.set_len(len).await.map_err(io_error(path))
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> DownloadError + '_ {
+    move |source| DownloadError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use crate::api::{
+        fixtures::serve,
+        types::model::{HashKind, Hashes},
+        CivitaiClient,
+    };
+
+    use super::{Download, DownloadError, Progress, QUARANTINE_DIR};
+
+    #[tokio::test]
+    async fn test_download() -> anyhow::Result<()> {
+        let (url, requests) = serve(&[
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 10\r\n\r\n0123456789",
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\n\
+            Connection: close\r\nContent-Length: 5\r\n\r\n56789",
+        ])
+        .await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-download-{}", std::process::id()));
+        let client = CivitaiClient::default();
+
+        // From scratch.
+        let download = Download::new(format!("{url}download/1"), dir.join("fresh.safetensors"));
+        let (tx, mut rx) = mpsc::channel(16);
+
+        let path = download.run(&client, Some(tx)).await?;
+        assert_eq!(std::fs::read_to_string(&path)?, "0123456789");
+        assert!(!download.part_path().exists());
+
+        let mut last = None;
+        while let Some(p) = rx.recv().await {
+            last = Some(p);
+        }
+        assert_eq!(
+            last,
+            Some(Progress {
+                downloaded: 10,
+                total: Some(10)
+            })
+        );
+
+        // Carrying on from a part file.
+        let download = Download::new(format!("{url}download/2"), dir.join("resumed.safetensors"));
+        std::fs::write(download.part_path(), "01234")?;
+
+        let path = download.run(&client, None).await?;
+        assert_eq!(std::fs::read_to_string(&path)?, "0123456789");
+        assert!(requests.lock().unwrap()[1].contains("range: bytes=5-"));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_progress_not_drained() -> anyhow::Result<()> {
+        let (url, _) = serve(&[
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 10\r\n\r\n0123456789",
+        ])
+        .await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-progress-{}", std::process::id()));
+        let client = CivitaiClient::default();
+
+        // Held on to, but never read from.
+        let (tx, rx) = mpsc::channel(1);
+
+        let download = Download::new(format!("{url}download/1"), dir.join("slow.safetensors"));
+        let path = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            download.run(&client, Some(tx)),
+        )
+        .await??;
+        assert_eq!(std::fs::read_to_string(&path)?, "0123456789");
+
+        drop(rx);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_range() -> anyhow::Result<()> {
+        let (url, requests) = serve(&[
+            // Starts later than asked: the part can not be carried on from.
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 7-9/10\r\n\
+            Connection: close\r\nContent-Length: 3\r\n\r\n789",
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 10\r\n\r\n0123456789",
+            // Starts earlier than asked: the end of the part is sent again.
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 3-9/10\r\n\
+            Connection: close\r\nContent-Length: 7\r\n\r\n3456789",
+            // Nothing was asked for, but a range came anyway.
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\n\
+            Connection: close\r\nContent-Length: 5\r\n\r\n56789",
+        ])
+        .await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-range-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let client = CivitaiClient::default();
+
+        let download = Download::new(format!("{url}download/1"), dir.join("later.safetensors"));
+        std::fs::write(download.part_path(), "01234")?;
+
+        let path = download.run(&client, None).await?;
+        assert_eq!(std::fs::read_to_string(&path)?, "0123456789");
+        {
+            let requests = requests.lock().unwrap();
+            assert!(requests[0].contains("range: bytes=5-"));
+            assert!(!requests[1].contains("range:"));
+        }
+
+        let download = Download::new(format!("{url}download/2"), dir.join("earlier.safetensors"));
+        std::fs::write(download.part_path(), "012XXXX")?;
+
+        let path = download.run(&client, None).await?;
+        assert_eq!(std::fs::read_to_string(&path)?, "0123456789");
+
+        let download = Download::new(format!("{url}download/3"), dir.join("unasked.safetensors"));
+        assert!(matches!(
+            download.run(&client, None).await,
+            Err(DownloadError::Range {
+                requested: 0,
+                got: 5
+            })
+        ));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hash_mismatch() -> anyhow::Result<()> {
+        let (url, _) = serve(&[
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 3\r\n\r\nabc",
+        ])
+        .await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-mismatch-{}", std::process::id()));
+        let client = CivitaiClient::default();
+
+        let download = Download {
+            hashes: Some(Hashes {
+                // "abc" would be 352441C2.
+                crc32: Some("DEADBEEF".to_string()),
+                ..Default::default()
+            }),
+            ..Download::new(format!("{url}download/3"), dir.join("tampered.safetensors"))
+        };
+
+        match download.run(&client, None).await {
+            Err(DownloadError::HashMismatch {
+                mismatch,
+                quarantined,
+            }) => {
+                assert_eq!(mismatch.kind, HashKind::Crc32);
+                assert_eq!(quarantined, dir.join(QUARANTINE_DIR).join("tampered.safetensors"));
+                assert_eq!(std::fs::read_to_string(quarantined)?, "abc");
+            }
+            other => panic!("Expected a hash mismatch, got {other:?}"),
+        }
+
+        assert!(!download.dest.exists());
+        assert!(!download.part_path().exists());
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -12,7 +12,7 @@ use super::MapLike;
 
 
 impl MapLike for Params {
-    fn into_map(self) -> std::collections::HashMap<String, String> {
+    fn into_map(self) -> Vec<(String, String)> {
         [
             ("query".to_string(), self.query)
         ].into_iter()
@@ -24,7 +24,7 @@ impl MapLike for Params {
 pub struct creators;
 
 impl Endpoint for creators {
-    const URL: &'static str = "https://civitai.com/api/v1/creators";
+    const PATH: &'static str = "creators";
 
     type Params = Params;
     type Response = Paginated<Creator>;
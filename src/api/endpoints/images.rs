@@ -1,11 +1,11 @@
 #![allow(non_camel_case_types)]
-use std::collections::HashMap;
+use std::fmt;
 
 use crate::api::{types::{model::Image, Nsfw, Period}, paginated::Paginated};
 
 use super::{Endpoint, MapLike};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum Sort {
     MostReactions,
     MostComments,
@@ -16,29 +16,104 @@ pub enum Sort {
 impl ToString for Sort {
     fn to_string(&self) -> String {
         match self {
-            Sort::MostReactions => "MostReactions",
-            Sort::MostComments => "MostComments",
+            Sort::MostReactions => "Most Reactions",
+            Sort::MostComments => "Most Comments",
             Sort::Newest => "Newest",
         }
         .to_string()
     }
 }
 
+///
+/// The API takes either a flag (any NSFW level or none at all),
+/// or the maximum level to include.
+///
+#[derive(Debug, Clone, Copy)]
+pub enum NsfwFilter {
+    Flag(bool),
+    Level(Nsfw),
+}
+
+impl From<bool> for NsfwFilter {
+    fn from(flag: bool) -> Self {
+        Self::Flag(flag)
+    }
+}
+
+impl From<Nsfw> for NsfwFilter {
+    fn from(level: Nsfw) -> Self {
+        Self::Level(level)
+    }
+}
+
+impl fmt::Display for NsfwFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NsfwFilter::Flag(flag) => write!(f, "{flag}"),
+            NsfwFilter::Level(level) => f.write_str(&level.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Params {
     pub query: Option<String>,
+
+    ///
+    /// The number of results to be returned per page (between 0 and 200)
+    ///
     pub limit: Option<usize>,
     pub post_id: Option<usize>,
     pub model_id: Option<usize>,
     pub model_version_id: Option<usize>,
     pub username: Option<String>,
-    pub nsfw: Option<Nsfw>,
+    pub nsfw: Option<NsfwFilter>,
     pub sort: Option<Sort>,
     pub period: Option<Period>,
+
+    ///
+    /// The page to fetch
+    ///
+    pub page: Option<usize>,
+
+    ///
+    /// Cursor to continue from (from `nextCursor` in the metadata),
+    /// stable even when new images are posted in the meantime
+    ///
+    pub cursor: Option<String>,
+}
+
+impl Params {
+    ///
+    /// The images of a post, newest first.
+    ///
+    pub fn for_post(post_id: usize) -> Self {
+        Self {
+            post_id: post_id.into(),
+            sort: Sort::Newest.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn for_user(username: impl ToString) -> Self {
+        Self {
+            username: username.to_string().into(),
+            sort: Sort::Newest.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn for_model_version(model_version_id: usize) -> Self {
+        Self {
+            model_version_id: model_version_id.into(),
+            sort: Sort::Newest.into(),
+            ..Default::default()
+        }
+    }
 }
 
 impl MapLike for Params {
-    fn into_map(self) -> std::collections::HashMap<String, String> {
+    fn into_map(self) -> Vec<(String, String)> {
         [
             ("query", self.query),
             ("limit", self.limit.map(|a| a.to_string())),
@@ -46,8 +121,11 @@ impl MapLike for Params {
             ("modelId", self.model_id.map(|a| a.to_string())),
             ("modelVersionId", self.model_version_id.map(|a| a.to_string())),
             ("username", self.username.map(|a| a.to_string())),
+            ("nsfw", self.nsfw.map(|a| a.to_string())),
             ("sort", self.sort.map(|a| a.to_string())),
             ("period", self.period.map(|a| a.to_string())),
+            ("page", self.page.map(|a| a.to_string())),
+            ("cursor", self.cursor),
         ]
         .into_iter()
         .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
@@ -58,9 +136,68 @@ impl MapLike for Params {
 pub struct images;
 
 impl Endpoint for images {
-    const URL: &'static str = "https://civitai.com/api/v1/images";
+    const PATH: &'static str = "images";
 
     type Params = Params;
 
     type Response = Paginated<Image>;
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::api::{
+        endpoints::MapLike,
+        types::{Nsfw, Period},
+    };
+
+    use super::{Params, Sort};
+
+    fn query(params: Params) -> String {
+        Url::parse_with_params("https://civitai.com/api/v1/images", params.into_map())
+            .unwrap()
+            .query()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn test_nsfw() {
+        assert_eq!(
+            query(Params {
+                nsfw: Some(false.into()),
+                ..Default::default()
+            }),
+            "nsfw=false"
+        );
+
+        assert_eq!(
+            query(Params {
+                nsfw: Some(Nsfw::Mature.into()),
+                ..Default::default()
+            }),
+            "nsfw=Mature"
+        );
+    }
+
+    #[test]
+    fn test_browsing() {
+        assert_eq!(query(Params::for_post(401)), "postId=401&sort=Newest");
+        assert_eq!(
+            query(Params::for_user("happy_maker")),
+            "username=happy_maker&sort=Newest"
+        );
+
+        assert_eq!(
+            query(Params {
+                limit: 50.into(),
+                sort: Sort::MostReactions.into(),
+                period: Period::AllTime.into(),
+                cursor: "10011|3002".to_string().into(),
+                ..Params::for_model_version(10011)
+            }),
+            "limit=50&modelVersionId=10011&sort=Most+Reactions&period=AllTime&cursor=10011%7C3002"
+        );
+    }
+}
@@ -1,28 +1,121 @@
 pub mod creators;
+pub mod model;
+pub mod model_versions;
 pub mod models;
 pub mod images;
+pub mod tags;
 
 use std::collections::HashMap;
 
-use futures::Future;
+use futures::{future::BoxFuture, FutureExt};
 use serde::de::DeserializeOwned;
 
-use super::send_request;
+use super::{
+    paginated::{Lenient, PageCache, PageIterator, Paginated},
+    CivitaiClient, Result,
+};
 
 pub trait MapLike
 where
     Self: Sized,
 {
-    fn into_map(self) -> HashMap<String, String>;
+    ///
+    /// The query parameters, in order.
+    /// Multi-valued filters repeat their key (`types=LORA&types=Checkpoint`).
+    ///
+    fn into_map(self) -> Vec<(String, String)>;
+
+    ///
+    /// Values for the `{placeholders}` in an endpoint's path.
+    ///
+    fn path_params(&self) -> HashMap<&'static str, String> {
+        HashMap::new()
+    }
 }
 
 pub trait Endpoint: Sized {
-    const URL: &'static str;
-    type Params: MapLike + Default;
-    type Response: DeserializeOwned;
+    ///
+    /// Path of the endpoint, relative to the client's base url.
+    /// May contain `{placeholders}`, filled in from [MapLike::path_params].
+    ///
+    const PATH: &'static str;
+    type Params: MapLike;
+    type Response: DeserializeOwned + Send;
+
+    fn path(params: &Self::Params) -> String {
+        params
+            .path_params()
+            .into_iter()
+            .fold(Self::PATH.to_string(), |path, (k, v)| {
+                path.replace(&format!("{{{k}}}"), &v)
+            })
+    }
+
+    ///
+    /// The returned future owns a clone of `client`,
+    /// so it can outlive the borrow.
+    ///
+    fn get(
+        client: &CivitaiClient,
+        params: Self::Params,
+    ) -> BoxFuture<'static, Result<Self::Response>> {
+        let client = client.clone();
+        let path = Self::path(&params);
+        let query = params.into_map();
+
+        async move {
+            let url = client.endpoint(&path)?;
+            client.send_request::<Self::Response>(url, query).await
+        }
+        .boxed()
+    }
 
-    fn get(params: Self::Params) -> impl Future<Output = anyhow::Result<Self::Response>> {
-        send_request::<Self::Response>(Self::URL, params.into_map())
+    ///
+    /// Like [Endpoint::get], but malformed items are set aside
+    /// (see [Paginated::errors]) instead of failing the whole page.
+    ///
+    fn get_lenient<T>(
+        client: &CivitaiClient,
+        params: Self::Params,
+    ) -> BoxFuture<'static, Result<Paginated<T>>>
+    where
+        Self: Endpoint<Response = Paginated<T>>,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let client = client.clone();
+        let path = Self::path(&params);
+        let query = params.into_map();
+
+        async move {
+            let url = client.endpoint(&path)?;
+            let Lenient(page) = client.send_request::<Lenient<T>>(url, query).await?;
+            Ok(page)
+        }
+        .boxed()
+    }
+
+    ///
+    /// Every item, across all pages, following either `nextPage` or `nextCursor`.
+    ///
+    fn stream<T>(client: &CivitaiClient, params: Self::Params) -> PageIterator<T>
+    where
+        Self: Endpoint<Response = Paginated<T>>,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let path = Self::path(&params);
+        PageIterator::new(client, path, params.into_map())
+    }
+
+    ///
+    /// An empty [PageCache] for the results, to be filled in as pages are needed.
+    ///
+    fn pages<T>(client: &CivitaiClient, params: Self::Params) -> PageCache<T>
+    where
+        Self: Endpoint<Response = Paginated<T>>,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let path = Self::path(&params);
+        PageCache::new(client, path, params.into_map())
     }
 }
 
@@ -30,34 +123,49 @@ pub trait Endpoint: Sized {
 mod tests {
     use futures::StreamExt;
 
-    use crate::api::endpoints::Endpoint;
+    use crate::api::{endpoints::Endpoint, fixtures};
 
     use super::creators::creators;
     #[tokio::test]
     async fn test_creators() -> anyhow::Result<()> {
-        let mut c = creators::get(Default::default()).await?.into_stream();
+        let client = fixtures::client();
+        let mut c = creators::get(&client, Default::default())
+            .await?
+            .into_stream(&client);
 
-        while let Some(creator) = c.next().await {
-            println!("{creator:?}")
+        let mut names = vec![];
+        while let Some(creator) = c.next().await.transpose()? {
+            println!("{creator:?}");
+            names.push(creator.username);
         }
 
+        assert_eq!(names, ["happy_maker", "another_artist", "quiet_artist"]);
+
         Ok(())
     }
 
     use super::models::{models, Params as ModelParam};
     #[tokio::test]
     async fn test_models() -> anyhow::Result<()> {
-        let mut c = models::get(ModelParam {
-            query: "happy".to_string().into(),
-            ..Default::default()
-        })
+        let client = fixtures::client();
+        let mut c = models::get(
+            &client,
+            ModelParam {
+                query: "happy".to_string().into(),
+                ..Default::default()
+            },
+        )
         .await?
-        .into_stream();
+        .into_stream(&client);
 
-        while let Some(model) = c.next().await {
-            println!("{model:#?}")
+        let mut ids = vec![];
+        while let Some(model) = c.next().await.transpose()? {
+            println!("{model:#?}");
+            ids.push(model.id);
         }
 
+        assert_eq!(ids, [1001, 1002, 1003]);
+
         Ok(())
     }
 }
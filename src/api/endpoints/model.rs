@@ -0,0 +1,39 @@
+#![allow(non_camel_case_types)]
+use std::collections::HashMap;
+
+use crate::api::types::Model;
+
+use super::{Endpoint, MapLike};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Params {
+    pub id: usize,
+}
+
+impl From<usize> for Params {
+    fn from(id: usize) -> Self {
+        Self { id }
+    }
+}
+
+impl MapLike for Params {
+    fn into_map(self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn path_params(&self) -> HashMap<&'static str, String> {
+        [("id", self.id.to_string())].into_iter().collect()
+    }
+}
+
+///
+/// A single model, by its id.
+///
+pub struct model;
+
+impl Endpoint for model {
+    const PATH: &'static str = "models/{id}";
+
+    type Params = Params;
+    type Response = Model;
+}
@@ -0,0 +1,84 @@
+#![allow(non_camel_case_types)]
+use std::collections::HashMap;
+
+use crate::api::types::model::{Hashes, Version};
+
+use super::{Endpoint, MapLike};
+
+#[derive(Debug, Clone, Copy)]
+pub struct IdParams {
+    pub id: usize,
+}
+
+impl From<usize> for IdParams {
+    fn from(id: usize) -> Self {
+        Self { id }
+    }
+}
+
+impl MapLike for IdParams {
+    fn into_map(self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn path_params(&self) -> HashMap<&'static str, String> {
+        [("id", self.id.to_string())].into_iter().collect()
+    }
+}
+
+///
+/// Any of the AutoV1, AutoV2, SHA256, CRC32 or BLAKE3 hashes of a file.
+///
+#[derive(Debug, Clone)]
+pub struct HashParams {
+    pub hash: String,
+}
+
+impl HashParams {
+    ///
+    /// Uses the strongest hash available.
+    ///
+    pub fn from_hashes(hashes: &Hashes) -> Option<Self> {
+        hashes.iter().next().map(|(_, hash)| hash.to_string().into())
+    }
+}
+
+impl From<String> for HashParams {
+    fn from(hash: String) -> Self {
+        Self { hash }
+    }
+}
+
+impl MapLike for HashParams {
+    fn into_map(self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn path_params(&self) -> HashMap<&'static str, String> {
+        [("hash", self.hash.clone())].into_iter().collect()
+    }
+}
+
+///
+/// A single model version, by its id.
+///
+pub struct by_id;
+
+impl Endpoint for by_id {
+    const PATH: &'static str = "model-versions/{id}";
+
+    type Params = IdParams;
+    type Response = Version;
+}
+
+///
+/// The model version a file belongs to, by the file's hash.
+///
+pub struct by_hash;
+
+impl Endpoint for by_hash {
+    const PATH: &'static str = "model-versions/by-hash/{hash}";
+
+    type Params = HashParams;
+    type Response = Version;
+}
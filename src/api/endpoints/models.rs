@@ -1,7 +1,9 @@
 #![allow(non_camel_case_types)]
+use std::fmt;
+
 use crate::api::{
     paginated::Paginated,
-    types::{model::Type, Model, Nsfw, Period},
+    types::{Model, Period},
 };
 
 use super::Endpoint;
@@ -28,61 +30,254 @@ impl Sorting {
     }
 }
 
+///
+/// What a model's license allows it to be used for commercially.
+///
+#[derive(Debug, Clone, Copy)]
+pub enum CommercialUse {
+    None,
+    Image,
+    Rent,
+    Sell,
+}
+
+impl fmt::Display for CommercialUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CommercialUse::None => "None",
+            CommercialUse::Image => "Image",
+            CommercialUse::Rent => "Rent",
+            CommercialUse::Sell => "Sell",
+        })
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct Params {
+    ///
+    /// The number of results to be returned per page (between 1 and 100)
+    ///
+    pub limit: Option<usize>,
+
+    ///
+    /// The page from which to start fetching models
+    ///
+    pub page: Option<usize>,
+
+    ///
+    /// Cursor to continue from (from `nextCursor` in the metadata)
+    ///
+    pub cursor: Option<String>,
+
+    ///
+    /// Search query to filter models by name
+    ///
     pub query: Option<String>,
+
+    ///
+    /// Search query to filter models by tag
+    ///
     pub tag: Option<String>,
+
+    ///
+    /// Search query to filter models by user
+    ///
     pub username: Option<String>,
-    pub types: Option<model::Type>,
+
+    ///
+    /// Only models of these types
+    ///
+    pub types: Vec<model::Type>,
+
+    ///
+    /// The order in which you wish to sort the results
+    ///
     pub sort: Option<Sorting>,
+
+    ///
+    /// The time frame in which the models will be sorted
+    ///
     pub period: Option<Period>,
+
+    ///
+    /// The rating you wish to filter the models with
+    ///
     pub rating: Option<f64>,
-    pub nsfw: Option<Nsfw>,
-}
 
-impl ToString for Type {
-    fn to_string(&self) -> String {
-        match self {
-            Type::Checkpoint => "Checkpoint",
-            Type::TextualInversion => "TextualInversion",
-            Type::Hypernetwork => "Hypernetwork",
-            Type::AestheticGradient => "AestheticGradient",
-            Type::Lora => "Lora",
-            Type::Controlnet => "Controlnet",
-            Type::Poses => "Poses",
-        }.to_string()
-    }
+    ///
+    /// Only the models the authenticated user has favorited
+    ///
+    pub favorites: Option<bool>,
+
+    ///
+    /// Only the models the authenticated user has hidden
+    ///
+    pub hidden: Option<bool>,
+
+    ///
+    /// Only include the primary file of each model version
+    ///
+    pub primary_file_only: Option<bool>,
+
+    ///
+    /// Filter by models which don't require credit
+    ///
+    pub allow_no_credit: Option<bool>,
+
+    ///
+    /// Filter by models which allow derivatives
+    ///
+    pub allow_derivatives: Option<bool>,
+
+    ///
+    /// Filter by models which allow a different license for derivatives
+    ///
+    pub allow_different_licenses: Option<bool>,
+
+    ///
+    /// Filter by the commercial use the models allow
+    ///
+    pub allow_commercial_use: Option<CommercialUse>,
+
+    ///
+    /// If false, models without safe images are hidden
+    ///
+    pub nsfw: Option<bool>,
+
+    ///
+    /// Only these models
+    ///
+    pub ids: Vec<usize>,
+
+    ///
+    /// Only models built on these base models (like `SD 1.5` or `SDXL 1.0`)
+    ///
+    pub base_models: Vec<String>,
 }
 
 impl MapLike for Params {
-    fn into_map(self) -> std::collections::HashMap<String, String> {
-        [
-            ("query".to_string(), self.query),
-            ("tag".to_string(), self.tag),
-            ("username".to_string(), self.username),
+    fn into_map(self) -> Vec<(String, String)> {
+        let single = [
+            ("limit", self.limit.map(|a| a.to_string())),
+            ("page", self.page.map(|a| a.to_string())),
+            ("cursor", self.cursor),
+            ("query", self.query),
+            ("tag", self.tag),
+            ("username", self.username),
+            ("sort", self.sort.map(|a| a.value().to_string())),
+            ("period", self.period.map(|a| a.to_string())),
+            ("rating", self.rating.map(|a| a.to_string())),
+            ("favorites", self.favorites.map(|a| a.to_string())),
+            ("hidden", self.hidden.map(|a| a.to_string())),
+            ("primaryFileOnly", self.primary_file_only.map(|a| a.to_string())),
+            ("allowNoCredit", self.allow_no_credit.map(|a| a.to_string())),
+            ("allowDerivatives", self.allow_derivatives.map(|a| a.to_string())),
             (
-                "types".to_string(),
-                self.types.map(|a| a.to_string()),
+                "allowDifferentLicenses",
+                self.allow_different_licenses.map(|a| a.to_string()),
             ),
             (
-                "period".to_string(),
-                self.period.map(|a| a.to_string()),
+                "allowCommercialUse",
+                self.allow_commercial_use.map(|a| a.to_string()),
             ),
-            ("rating".to_string(), self.rating.map(|a| a.to_string())),
-            ("nsfw".to_string(), self.nsfw.map(|a| a.to_string())),
+            ("nsfw", self.nsfw.map(|a| a.to_string())),
         ]
         .into_iter()
-        .filter_map(|(k, v)| v.map(|v| (k, v)))
-        .collect()
+        .filter_map(|(k, v)| v.map(|v| (k, v)));
+
+        let multi = self
+            .types
+            .iter()
+            .map(|a| ("types", a.to_string()))
+            .chain(self.ids.iter().map(|a| ("ids", a.to_string())))
+            .chain(self.base_models.into_iter().map(|a| ("baseModels", a)));
+
+        single
+            .chain(multi)
+            .map(|(k, v)| (k.to_string(), v))
+            .collect()
     }
 }
 
 pub struct models;
 
 impl Endpoint for models {
-    const URL: &'static str = "https://civitai.com/api/v1/models";
+    const PATH: &'static str = "models";
 
     type Params = Params;
     type Response = Paginated<Model>;
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::api::{endpoints::MapLike, types::{model::Type, Period}};
+
+    use super::{CommercialUse, Params, Sorting};
+
+    fn query(params: Params) -> String {
+        Url::parse_with_params("https://civitai.com/api/v1/models", params.into_map())
+            .unwrap()
+            .query()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(query(Params::default()), "");
+    }
+
+    #[test]
+    fn test_search() {
+        assert_eq!(
+            query(Params {
+                query: "happy dog".to_string().into(),
+                tag: "animals".to_string().into(),
+                username: "someone".to_string().into(),
+                sort: Sorting::MostDownloaded.into(),
+                period: Period::Week.into(),
+                limit: 20.into(),
+                page: 2.into(),
+                ..Default::default()
+            }),
+            "limit=20&page=2&query=happy+dog&tag=animals&username=someone&sort=Most+Downloaded&period=Week"
+        );
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(
+            query(Params {
+                cursor: "abc|123".to_string().into(),
+                rating: 4.5.into(),
+                favorites: true.into(),
+                hidden: false.into(),
+                primary_file_only: true.into(),
+                allow_no_credit: true.into(),
+                allow_derivatives: false.into(),
+                allow_different_licenses: true.into(),
+                allow_commercial_use: CommercialUse::Sell.into(),
+                nsfw: false.into(),
+                ..Default::default()
+            }),
+            "cursor=abc%7C123&rating=4.5&favorites=true&hidden=false&primaryFileOnly=true\
+            &allowNoCredit=true&allowDerivatives=false&allowDifferentLicenses=true\
+            &allowCommercialUse=Sell&nsfw=false"
+        );
+    }
+
+    #[test]
+    fn test_multi_valued() {
+        assert_eq!(
+            query(Params {
+                types: vec![Type::Lora, Type::Checkpoint],
+                ids: vec![1, 2],
+                base_models: vec!["SD 1.5".to_string(), "SDXL 1.0".to_string()],
+                ..Default::default()
+            }),
+            "types=LORA&types=Checkpoint&ids=1&ids=2&baseModels=SD+1.5&baseModels=SDXL+1.0"
+        );
+    }
+}
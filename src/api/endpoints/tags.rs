@@ -0,0 +1,33 @@
+#![allow(non_camel_case_types)]
+use crate::api::{paginated::Paginated, types::Tag};
+
+use super::{Endpoint, MapLike};
+
+#[derive(Debug, Default)]
+pub struct Params {
+    pub query: Option<String>,
+    pub limit: Option<usize>,
+    pub page: Option<usize>,
+}
+
+impl MapLike for Params {
+    fn into_map(self) -> Vec<(String, String)> {
+        [
+            ("query", self.query),
+            ("limit", self.limit.map(|a| a.to_string())),
+            ("page", self.page.map(|a| a.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
+        .collect()
+    }
+}
+
+pub struct tags;
+
+impl Endpoint for tags {
+    const PATH: &'static str = "tags";
+
+    type Params = Params;
+    type Response = Paginated<Tag>;
+}
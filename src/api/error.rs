@@ -0,0 +1,178 @@
+//!
+//! Errors surfaced by the API layer.
+//!
+
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
+
+///
+/// How much of a response body is kept around in an error.
+///
+const EXCERPT_LEN: usize = 512;
+
+pub type Result<T> = std::result::Result<T, ApiError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    ///
+    /// The server answered, but not with a success status.
+    ///
+    #[error("{url} responded with {status}: {body}")]
+    Status {
+        url: String,
+        status: StatusCode,
+
+        ///
+        /// The start of the response body
+        ///
+        body: String,
+    },
+
+    ///
+    /// The response was not in the shape we expected.
+    ///
+    #[error("Could not decode `{path}` from {url}: {source}")]
+    Decode {
+        url: String,
+
+        ///
+        /// Path of the failing field, like `items[3].modelVersions[0].files`
+        ///
+        path: String,
+
+        ///
+        /// The start of the response body
+        ///
+        body: String,
+
+        #[source]
+        source: serde_json::Error,
+    },
+
+    ///
+    /// Connection, TLS, timeout, etc.
+    ///
+    #[error("Request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    #[error("Invalid url `{0}`")]
+    InvalidUrl(String),
+
+    ///
+    /// The client is offline, and has nothing cached for this url.
+    ///
+    #[error("{0} is not cached, and the client is offline")]
+    Offline(String),
+
+    ///
+    /// Reading or writing a recorded response failed
+    /// (when replaying, the response was probably never recorded).
+    ///
+    #[error("Recording {}: {source}", path.display())]
+    Recording {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl ApiError {
+    pub(crate) fn status(url: impl ToString, status: StatusCode, body: &str) -> Self {
+        Self::Status {
+            url: url.to_string(),
+            status,
+            body: excerpt(body),
+        }
+    }
+
+    pub(crate) fn decode(
+        url: impl ToString,
+        body: &str,
+        err: serde_path_to_error::Error<serde_json::Error>,
+    ) -> Self {
+        Self::Decode {
+            url: url.to_string(),
+            path: err.path().to_string(),
+            body: excerpt(body),
+            source: err.into_inner(),
+        }
+    }
+
+    ///
+    /// The HTTP status, if the server got to answer.
+    ///
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Self::Status { status, .. } => Some(*status),
+            Self::Transport(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status_code() == Some(StatusCode::NOT_FOUND)
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.status_code() == Some(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    pub fn is_decode(&self) -> bool {
+        matches!(self, Self::Decode { .. })
+    }
+}
+
+fn excerpt(body: &str) -> String {
+    match body.char_indices().nth(EXCERPT_LEN) {
+        Some((i, _)) => format!("{}…", &body[..i]),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::ApiError;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Outer {
+        items: Vec<Inner>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Inner {
+        id: usize,
+    }
+
+    #[test]
+    fn test_decode_path() {
+        let body = r#"{ "items": [{ "id": 1 }, { "id": "two" }] }"#;
+        let err = serde_path_to_error::deserialize::<_, Outer>(
+            &mut serde_json::Deserializer::from_str(body),
+        )
+        .unwrap_err();
+
+        match ApiError::decode("http://localhost/models", body, err) {
+            ApiError::Decode { path, .. } => assert_eq!(path, "items[1].id"),
+            other => panic!("Expected a decode error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let long = "a".repeat(2000);
+        let err = ApiError::status("http://localhost", reqwest::StatusCode::NOT_FOUND, &long);
+
+        assert!(err.is_not_found());
+        assert!(!err.is_rate_limited());
+
+        if let ApiError::Status { body, .. } = err {
+            assert_eq!(body.chars().count(), super::EXCERPT_LEN + 1);
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//!
+//! Replays the canned responses in `fixtures/`,
+//! so the tests never touch the network.
+//!
+//! Fixtures are named the way [Recorder::key] names recordings,
+//! so new ones can be captured by pointing `CIVITAI_RECORD_DIR` at a scratch directory.
+//!
+//! For what needs a live server (retries, caching, downloads),
+//! [serve] answers with scripted responses on a local port.
+//!
+
+use std::sync::{Arc, Mutex};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use super::{client::Config, recorder::Recorder, CivitaiClient};
+
+pub(crate) const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+pub(crate) fn client() -> CivitaiClient {
+    CivitaiClient::new(Config {
+        recorder: Some(Recorder::replay(DIR)),
+        ..Default::default()
+    })
+    .expect("Valid fixture client!")
+}
+
+pub(crate) type Requests = Arc<Mutex<Vec<String>>>;
+
+///
+/// Serves `responses` in turn, one per connection,
+/// and keeps the requests it got.
+///
+pub(crate) async fn serve(responses: &'static [&'static str]) -> anyhow::Result<(String, Requests)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/api/v1/", listener.local_addr()?);
+    let requests = Requests::default();
+
+    let log = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut conn, _)) = listener.accept().await {
+            let mut buf = [0; 4096];
+            let len = conn.read(&mut buf).await.unwrap_or_default();
+
+            let i = {
+                let mut log = log.lock().unwrap();
+                log.push(String::from_utf8_lossy(&buf[..len]).to_lowercase());
+                log.len() - 1
+            };
+
+            let res = responses[i.min(responses.len() - 1)];
+            let _ = conn.write_all(res.as_bytes()).await;
+        }
+    });
+
+    Ok((url, requests))
+}
+
+mod tests {
+    use std::path::Path;
+
+    use futures::{Stream, StreamExt, TryStreamExt};
+    use serde::{de::DeserializeOwned, Serialize};
+
+    use crate::api::{
+        endpoints::{
+            creators::creators, images, model::model, model_versions, models, tags, Endpoint,
+        },
+        types::model::{FloatingPoint, Format, HashKind, Image, Mode, ScanResult, Type, Version},
+        types::{Creator, Model, Nsfw, Tag},
+        ApiError, Paginated,
+    };
+
+    use super::{client, DIR};
+
+    ///
+    /// Decoding, encoding and decoding again must give back the same JSON.
+    ///
+    fn round_trip<T: Serialize + DeserializeOwned>(fixture: &str) -> anyhow::Result<()> {
+        let raw = std::fs::read_to_string(Path::new(DIR).join(fixture))?;
+
+        let first = serde_json::to_value(serde_json::from_str::<T>(&raw)?)?;
+        let second = serde_json::to_value(serde_json::from_value::<T>(first.clone())?)?;
+
+        assert_eq!(first, second, "{fixture} did not round-trip");
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        round_trip::<Paginated<Model>>("models.query-happy.a844ae6d.json")?;
+        round_trip::<Paginated<Model>>("models.page-2.query-happy.b81f69d7.json")?;
+        round_trip::<Model>("models.1001.4cf6855e.json")?;
+        round_trip::<Version>("model-versions.10011.5bc9c2f6.json")?;
+        round_trip::<Paginated<Image>>("images.modelVersionId-10011.71a57484.json")?;
+        round_trip::<Paginated<Creator>>("creators.b2b2d594.json")?;
+        round_trip::<Paginated<Tag>>("tags.limit-3.559fe652.json")?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model() -> anyhow::Result<()> {
+        let client = client();
+        let page = models::models::get(
+            &client,
+            models::Params {
+                query: "happy".to_string().into(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let (items, meta) = page.first_few();
+        let items = items.collect::<Vec<_>>();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(meta.total_items, 3);
+        assert_eq!(meta.total_pages, 2);
+        assert!(meta.next_page.is_some());
+        assert!(meta.prev_page.is_none());
+
+        let checkpoint = items[0];
+        assert_eq!(checkpoint.name, "Happy Checkpoint");
+        assert!(matches!(checkpoint._type, Type::Checkpoint));
+        assert!(checkpoint.mode.is_none());
+        assert_eq!(checkpoint.creator.username, "happy_maker");
+        assert!(checkpoint.creator.image.is_some());
+        assert_eq!(checkpoint.stats.favorite_count, 812);
+        assert_eq!(checkpoint.versions.len(), 2);
+
+        let version = &checkpoint.versions[0];
+        assert_eq!(version.id, 10011);
+        assert!(version.created_at.is_some());
+        assert_eq!(version.files.len(), 2);
+
+        let pickle = &version.files[1];
+        assert_eq!(pickle.size_kb, Some(4165285.2));
+        assert_eq!(pickle.primary, Some(false));
+        assert!(matches!(pickle.pickle_scan_result, Some(ScanResult::Success)));
+        assert!(pickle.scanned_at.is_some());
+        assert!(matches!(
+            pickle.metadata.as_ref().and_then(|m| m.format.as_ref()),
+            Some(Format::PickleTensor)
+        ));
+
+        let lora = items[1];
+        assert!(matches!(lora._type, Type::Lora));
+        assert_eq!(lora.versions[0].trained_words, ["happy face", "smiling"]);
+        assert!(lora.versions[0].files[0]
+            .metadata
+            .as_ref()
+            .is_some_and(|m| m.fp.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_values() -> anyhow::Result<()> {
+        let raw = std::fs::read_to_string(Path::new(DIR).join("models.query-happy.a844ae6d.json"))?
+            .replace(r#""type": "Checkpoint""#, r#""type": "Hologram""#)
+            .replace(r#""fp": "fp16""#, r#""fp": "bf16""#)
+            .replace(r#""format": "PickleTensor""#, r#""format": "Tarball""#);
+
+        let page = serde_json::from_str::<Paginated<Model>>(&raw)?;
+        let (items, _) = page.first_few();
+        let first = items.into_iter().next().unwrap();
+
+        assert_eq!(first._type, Type::Unknown("Hologram".to_string()));
+
+        let files = &first.versions[0].files;
+        let meta = |i: usize| files[i].metadata.as_ref().unwrap();
+        assert_eq!(meta(0).fp, Some(FloatingPoint::Bf16));
+        assert_eq!(meta(1).format, Some(Format::Unknown("Tarball".to_string())));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_by_id() -> anyhow::Result<()> {
+        let client = client();
+        let m = model::get(&client, 1001.into()).await?;
+
+        assert_eq!(m.name, "Happy Checkpoint");
+        assert_eq!(m.refresh(&client).await?.versions.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_version() -> anyhow::Result<()> {
+        let client = client();
+        let version = model_versions::by_id::get(&client, 10011.into()).await?;
+
+        assert_eq!(version.model_id, Some(1001));
+        let parent = version.model.as_ref().unwrap();
+        assert_eq!(parent.name, "Happy Checkpoint");
+        assert!(matches!(parent._type, Type::Checkpoint));
+
+        let hashes = &version.files[0].hashes;
+        assert_eq!(hashes.get(HashKind::AutoV2), Some("6E9C2B4A1F"));
+
+        let params = model_versions::HashParams::from_hashes(hashes).unwrap();
+        assert_eq!(Some(params.hash.as_str()), hashes.get(HashKind::Sha256));
+
+        let by_hash = model_versions::by_hash::get(&client, params).await?;
+        assert_eq!(by_hash.id, version.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_next_page() -> anyhow::Result<()> {
+        let client = client();
+        let models = models::models::get(
+            &client,
+            models::Params {
+                query: "happy".to_string().into(),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_stream(&client)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        let embedding = models.last().unwrap();
+        assert_eq!(models.len(), 3);
+        assert!(matches!(embedding._type, Type::TextualInversion));
+        assert!(matches!(embedding.mode, Some(Mode::Archived)));
+        assert!(matches!(
+            embedding.versions[0].files[0].virus_scan_result,
+            Some(ScanResult::Pending)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_images() -> anyhow::Result<()> {
+        let client = client();
+        let page = images::images::get(
+            &client,
+            images::Params {
+                model_version_id: 10011.into(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let (items, meta) = page.first_few();
+        let items = items.collect::<Vec<_>>();
+
+        assert_eq!(items.len(), 2);
+        assert!(meta.next_page.is_none());
+        assert_eq!(meta.next_cursor.as_deref(), Some("3002"));
+
+        assert_eq!(items[0].width, 512);
+        assert_eq!(items[0].post_id, Some(401));
+        assert_eq!(items[0].username.as_deref(), Some("happy_maker"));
+        assert!(items[0].created_at.is_some());
+        assert!(matches!(items[1].nsfw_level, Nsfw::Soft));
+        assert_eq!(items[0].height, 768);
+
+        let meta = items[0].meta.as_ref().unwrap();
+        assert_eq!(meta.seed, Some(1234567890));
+        assert_eq!(meta.steps, Some(28));
+        assert_eq!(meta.cfg_scale, Some(7.0));
+        assert_eq!(meta.sampler.as_deref(), Some("DPM++ 2M Karras"));
+        assert_eq!(meta.model_hash.as_deref(), Some("6e9c2b4a1f"));
+        assert_eq!(meta.resources.len(), 2);
+        assert_eq!(meta.resources[1].weight, Some(0.8));
+        assert_eq!(meta.extra["Clip skip"], "2");
+
+        assert_eq!(
+            meta.parameters(),
+            "a happy dog in a field of flowers, masterpiece\n\
+            Negative prompt: lowres, blurry\n\
+            Steps: 28, Sampler: DPM++ 2M Karras, CFG scale: 7, Seed: 1234567890, Size: 512x768, \
+            Model hash: 6e9c2b4a1f, Model: happyCheckpoint_v20, Clip skip: 2"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_images_cursor() -> anyhow::Result<()> {
+        let client = client();
+        let mut stream = images::images::stream(
+            &client,
+            images::Params {
+                model_version_id: 10011.into(),
+                ..Default::default()
+            },
+        );
+
+        let mut ids = vec![];
+        while let Some(image) = stream.try_next().await? {
+            ids.push(image.id);
+        }
+
+        // The second page only exists as `cursor=3002`.
+        assert_eq!(ids, [3001, 3002, 3003]);
+        assert_eq!(stream.size_hint(), (0, Some(0)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_errors() -> anyhow::Result<()> {
+        let client = client();
+
+        // An empty page is skipped, onto the recorded `creators?page=2`.
+        let page = serde_json::from_str::<Paginated<Creator>>(
+            r#"{
+                "items": [],
+                "metadata": { "nextPage": "https://civitai.com/api/v1/creators?page=2" }
+            }"#,
+        )?;
+        let names = page
+            .into_stream(&client)
+            .map_ok(|c| c.username)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(names, ["quiet_artist"]);
+
+        // A page which can not be fetched ends the stream, with the error.
+        let page = serde_json::from_str::<Paginated<Creator>>(
+            r#"{
+                "items": [{ "username": "first", "modelCount": 1, "link": "" }],
+                "metadata": {
+                    "totalItems": 2,
+                    "nextPage": "https://civitai.com/api/v1/creators?page=404"
+                }
+            }"#,
+        )?;
+        let mut stream = page.into_stream(&client);
+        assert_eq!(stream.size_hint(), (1, Some(2)));
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert_eq!(stream.size_hint(), (0, Some(1)));
+        assert!(matches!(stream.next().await, Some(Err(ApiError::Recording { .. }))));
+        assert!(stream.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() -> anyhow::Result<()> {
+        let client = client();
+
+        let ids = models::models::stream(
+            &client,
+            models::Params {
+                query: "happy".to_string().into(),
+                ..Default::default()
+            },
+        )
+        .prefetch(2)
+        .map_ok(|m| m.id)
+        .try_collect::<Vec<_>>()
+        .await?;
+        assert_eq!(ids, [1001, 1002, 1003]);
+
+        let mut stream = images::images::stream(
+            &client,
+            images::Params {
+                model_version_id: 10011.into(),
+                ..Default::default()
+            },
+        )
+        .prefetch(1);
+
+        let first = stream.try_next().await?.unwrap();
+        assert_eq!(first.id, 3001);
+
+        // Dropping the stream half-way must not hang or panic the background task.
+        drop(stream);
+
+        let page = serde_json::from_str::<Paginated<Creator>>(
+            r#"{
+                "items": [{ "username": "first", "modelCount": 1, "link": "" }],
+                "metadata": { "nextPage": "https://civitai.com/api/v1/creators?page=404" }
+            }"#,
+        )?;
+        let results = page.into_stream(&client).prefetch(3).collect::<Vec<_>>().await;
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[1], Err(ApiError::Recording { .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_page_cache() -> anyhow::Result<()> {
+        let client = client();
+
+        let mut models = models::models::pages(
+            &client,
+            models::Params {
+                query: "happy".to_string().into(),
+                ..Default::default()
+            },
+        );
+        assert!(models.get(0).is_none());
+
+        let last = models.get_or_load(2).await?.map(|m| m.id);
+        assert_eq!(last, Some(1003));
+        assert_eq!(models.total_pages(), Some(2));
+        assert_eq!(models.get(0).map(|m| m.id), Some(1001));
+        assert!(models.load(3).await?.is_none());
+
+        // Pages can also be fetched, and put in, by hand.
+        let mut tags = tags::tags::pages(
+            &client,
+            tags::Params {
+                limit: 3.into(),
+                ..Default::default()
+            },
+        );
+        tags.load(1).await?;
+        tags.insert(2, tags.fetch(2).unwrap().await?);
+        assert_eq!(tags.get(4).map(|t| t.name.as_str()), Some("face"));
+
+        // Cursor based ones are walked through.
+        let mut images = images::images::pages(
+            &client,
+            images::Params {
+                model_version_id: 10011.into(),
+                ..Default::default()
+            },
+        );
+        assert!(images.fetch(2).is_none());
+        assert_eq!(images.get_or_load(2).await?.map(|i| i.id), Some(3003));
+        assert_eq!(images.page(2).map(|p| p.first_few().0.count()), Some(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_creators() -> anyhow::Result<()> {
+        let client = client();
+        let page = creators::get(&client, Default::default()).await?;
+
+        let (items, _) = page.first_few();
+        let items = items.collect::<Vec<_>>();
+
+        assert_eq!(items[0].model_count, Some(3));
+        assert!(items[0].link.ends_with("username=happy_maker"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tags() -> anyhow::Result<()> {
+        let client = client();
+        let tags = tags::tags::get(
+            &client,
+            tags::Params {
+                limit: 3.into(),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_stream(&client)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        assert_eq!(tags.len(), 5);
+        assert_eq!(tags[0].name, "character");
+        assert_eq!(tags[0].model_count, Some(15890));
+        assert!(tags[4].model_count.is_none());
+        assert!(tags[3].link.ends_with("tag=happy"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_fixture() {
+        let client = client();
+        let res = models::models::get(
+            &client,
+            models::Params {
+                query: "never recorded".to_string().into(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert!(matches!(res, Err(ApiError::Recording { .. })));
+    }
+}
@@ -0,0 +1,410 @@
+//!
+//! What is installed locally: model files found in the model directories,
+//! identified by their hash.
+//!
+//! Hashing a model takes a while, so hashes are kept in a [HashCache],
+//! and only files which changed (going by their size and modification time) are hashed again.
+//!
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cache::Cache,
+    download::QUARANTINE_DIR,
+    endpoints::{model::model, model_versions, Endpoint},
+    types::{
+        model::{File, HashKind, Version},
+        Model,
+    },
+    verify, ApiError, CivitaiClient,
+};
+
+///
+/// Extensions of the files taken for models.
+///
+pub const EXTENSIONS: [&str; 3] = ["safetensors", "ckpt", "pt"];
+
+const HASH_CACHE: &str = "hashes.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+
+    #[error("Reading {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: io::Error,
+    },
+}
+
+type Result<T> = std::result::Result<T, LibraryError>;
+
+#[derive(Debug, Clone)]
+pub struct Library {
+    ///
+    /// Where models are looked for (including subdirectories)
+    ///
+    pub dirs: Vec<PathBuf>,
+
+    ///
+    /// Where hashes are kept between scans, if anywhere
+    ///
+    pub hash_cache: Option<PathBuf>,
+}
+
+///
+/// A local file Civitai knows.
+///
+#[derive(Debug, Clone)]
+pub struct Installed {
+    pub path: PathBuf,
+    pub sha256: String,
+
+    ///
+    /// The version the file belongs to
+    ///
+    pub version: Version,
+}
+
+///
+/// A local file Civitai does not know (or one it could not be asked about).
+///
+#[derive(Debug)]
+pub struct Unknown {
+    pub path: PathBuf,
+    pub sha256: Option<String>,
+
+    ///
+    /// Why the file could not be identified, if not because Civitai has never seen it.
+    ///
+    pub error: Option<LibraryError>,
+}
+
+#[derive(Debug, Default)]
+pub struct Inventory {
+    pub installed: Vec<Installed>,
+    pub unknown: Vec<Unknown>,
+}
+
+impl Library {
+    pub fn new(dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            dirs: dirs.into_iter().map(Into::into).collect(),
+            hash_cache: Cache::default_dir().map(|dir| dir.join(HASH_CACHE)),
+        }
+    }
+
+    ///
+    /// The directories in `CIVITAI_MODEL_DIRS` (separated like `PATH`).
+    ///
+    pub fn from_env() -> Self {
+        let dirs = std::env::var_os("CIVITAI_MODEL_DIRS")
+            .map(|dirs| std::env::split_paths(&dirs).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        Self::new(dirs)
+    }
+
+    ///
+    /// Every model file in the library, in order.
+    /// Hidden directories (like the quarantine) are skipped.
+    ///
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![];
+        let mut todo = self.dirs.clone();
+
+        while let Some(dir) = todo.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(kind) = entry.file_type() else {
+                    continue;
+                };
+
+                if kind.is_dir() {
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    if !hidden && entry.file_name() != QUARANTINE_DIR {
+                        todo.push(path);
+                    }
+                } else if is_model(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    ///
+    /// Hashes every model file (or takes its hash from the cache),
+    /// and asks Civitai which version each belongs to.
+    ///
+    pub async fn scan(&self, client: &CivitaiClient) -> Inventory {
+        let mut hashes = self
+            .hash_cache
+            .as_deref()
+            .map(HashCache::load)
+            .unwrap_or_default();
+
+        let mut inventory = Inventory::default();
+
+        for path in self.files() {
+            let sha256 = match hashes.sha256(&path).await {
+                Ok(sha256) => sha256,
+                Err(error) => {
+                    inventory.unknown.push(Unknown {
+                        path,
+                        sha256: None,
+                        error: Some(error),
+                    });
+                    continue;
+                }
+            };
+
+            match model_versions::by_hash::get(client, sha256.clone().into()).await {
+                Ok(version) => inventory.installed.push(Installed {
+                    path,
+                    sha256,
+                    version,
+                }),
+                Err(error) => inventory.unknown.push(Unknown {
+                    path,
+                    sha256: Some(sha256),
+                    error: Some(error).filter(|e| !e.is_not_found()).map(Into::into),
+                }),
+            }
+        }
+
+        if let Some(path) = &self.hash_cache {
+            // Losing the cache only costs time.
+            let _ = hashes.save(path);
+        }
+
+        inventory
+    }
+}
+
+impl Installed {
+    ///
+    /// The whole model the file belongs to (if the version says which).
+    ///
+    pub fn model(
+        &self,
+        client: &CivitaiClient,
+    ) -> Option<BoxFuture<'static, super::Result<Model>>> {
+        let id = self.version.model_id?;
+        Some(model::get(client, id.into()))
+    }
+
+    ///
+    /// The file of the version this is.
+    ///
+    pub fn file(&self) -> Option<&File> {
+        self.version.files.iter().find(|file| {
+            file.hashes
+                .get(HashKind::Sha256)
+                .is_some_and(|h| h.eq_ignore_ascii_case(&self.sha256))
+        })
+    }
+}
+
+///
+/// SHA256s of files, by path, valid while their size and modification time stay the same.
+///
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HashCache {
+    entries: HashMap<PathBuf, CachedHash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct CachedHash {
+    size: u64,
+
+    ///
+    /// Nanoseconds since the epoch
+    ///
+    modified: u64,
+    sha256: String,
+}
+
+impl HashCache {
+    ///
+    /// An empty cache if there is none at `path` (or it cannot be read).
+    ///
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let raw = serde_json::to_vec(self).map_err(io::Error::other)?;
+        fs::write(path, raw)
+    }
+
+    ///
+    /// The cached hash of `path`, unless the file changed since.
+    ///
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        let (size, modified) = stat(path).ok()?;
+
+        self.entries
+            .get(path)
+            .filter(|e| e.size == size && e.modified == modified)
+            .map(|e| e.sha256.as_str())
+    }
+
+    ///
+    /// The SHA256 of `path`, from the cache or hashing the file.
+    ///
+    pub async fn sha256(&mut self, path: &Path) -> Result<String> {
+        if let Some(sha256) = self.get(path) {
+            return Ok(sha256.to_string());
+        }
+
+        let io_error = |source| LibraryError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let (size, modified) = stat(path).map_err(io_error)?;
+
+        let hashes = {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || verify::compute(&path, &[HashKind::Sha256]))
+        }
+        .await
+        .expect("Hashing panicked!")
+        .map_err(io_error)?;
+
+        let sha256 = hashes.sha256.unwrap_or_default();
+        self.entries.insert(
+            path.to_path_buf(),
+            CachedHash {
+                size,
+                modified,
+                sha256: sha256.clone(),
+            },
+        );
+
+        Ok(sha256)
+    }
+}
+
+fn is_model(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+///
+/// The size and modification time of `path`.
+///
+fn stat(path: &Path) -> io::Result<(u64, u64)> {
+    let meta = fs::metadata(path)?;
+    let modified = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+
+    Ok((meta.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{client::Config, fixtures::serve, throttle::RetryPolicy, CivitaiClient};
+
+    use super::{HashCache, Library};
+
+    #[tokio::test]
+    async fn test_scan() -> anyhow::Result<()> {
+        let (base_url, requests) = serve(&[
+            "HTTP/1.1 404 Not Found\r\nConnection: close\r\nContent-Length: 2\r\n\r\n{}",
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 103\r\n\r\n\
+            {\"id\":10011,\"modelId\":1001,\"name\":\"v1\",\"createdAt\":null,\
+            \"downloadUrl\":\"x\",\"trainedWords\":[],\"files\":[]}",
+        ])
+        .await?;
+
+        let client = CivitaiClient::new(Config {
+            base_url,
+            retry: RetryPolicy::none(),
+            ..Default::default()
+        })?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-library-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lora/.quarantine"))?;
+        std::fs::write(dir.join("a.safetensors"), "abc")?;
+        std::fs::write(dir.join("lora/b.ckpt"), "def")?;
+        std::fs::write(dir.join("lora/.quarantine/c.ckpt"), "ghi")?;
+        std::fs::write(dir.join("notes.txt"), "jkl")?;
+
+        let library = Library {
+            dirs: vec![dir.clone()],
+            hash_cache: Some(dir.join("hashes.json")),
+        };
+        assert_eq!(
+            library.files(),
+            [dir.join("a.safetensors"), dir.join("lora/b.ckpt")]
+        );
+
+        // `b.ckpt` was hashed before (as something else), and has not changed since.
+        let mut cache = HashCache::default();
+        cache.sha256(&dir.join("lora/b.ckpt")).await?;
+        cache.entries.values_mut().for_each(|e| e.sha256 = "CAFE".to_string());
+        cache.save(&dir.join("hashes.json"))?;
+
+        let inventory = library.scan(&client).await;
+
+        assert_eq!(inventory.unknown.len(), 1);
+        let unknown = &inventory.unknown[0];
+        assert_eq!(unknown.path, dir.join("a.safetensors"));
+        assert!(unknown.error.is_none());
+        assert_eq!(
+            unknown.sha256.as_deref(),
+            Some("BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD")
+        );
+
+        assert_eq!(inventory.installed.len(), 1);
+        assert_eq!(inventory.installed[0].sha256, "CAFE");
+        assert_eq!(inventory.installed[0].version.id, 10011);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("get /api/v1/model-versions/by-hash/ba7816bf"));
+        assert!(requests[1].starts_with("get /api/v1/model-versions/by-hash/cafe "));
+
+        // Both are cached now.
+        let cache = HashCache::load(&dir.join("hashes.json"));
+        assert!(cache.get(&dir.join("a.safetensors")).is_some());
+
+        // Until they change.
+        std::fs::write(dir.join("a.safetensors"), "abcd")?;
+        assert!(cache.get(&dir.join("a.safetensors")).is_none());
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
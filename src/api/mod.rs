@@ -1,17 +1,28 @@
-use std::{collections::HashMap, fs};
-
-use lazy_static::lazy_static;
-use reqwest::{Client, IntoUrl, RequestBuilder};
-use serde::{de::DeserializeOwned, Deserialize};
-use serde_aux::field_attributes::deserialize_number_from_string;
+use serde::{Deserialize, Serialize};
 
+pub mod cache;
+pub mod client;
+pub mod download;
 pub mod endpoints;
+pub mod error;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod library;
 pub mod paginated;
+pub mod recorder;
+pub mod safety;
+pub mod safetensors;
+pub mod sidecar;
+pub mod throttle;
 pub mod types;
+pub mod updates;
 pub mod utils;
+pub mod verify;
 
-pub use paginated::{PageIterator, Paginated};
-#[derive(Debug, Deserialize, Clone)]
+pub use client::CivitaiClient;
+pub use error::{ApiError, Result};
+pub use paginated::{ItemError, Lenient, PageCache, PageIterator, Paginated};
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PaginationMeta {
     ///
@@ -47,47 +58,38 @@ pub struct PaginationMeta {
     /// The url to get the previous batch of items
     ///
     pub prev_page: Option<String>,
-}
-
-pub async fn send_request<T: DeserializeOwned>(
-    url: impl IntoUrl,
-    params: impl IntoIterator<Item = (String, String)>,
-) -> anyhow::Result<T> {
-    let txt = Client::new()
-        .get(url)
-        .header("Content-Type", "application/json")
-        .query(&params.into_iter().collect::<Vec<_>>())
-        .send()
-        .await?
-        .text()
-        .await?;
-
-    fs::write(".DEBUG", &txt).expect("Valid debug write!");
 
-    serde_json::from_str(&txt).map_err(anyhow::Error::from)
+    ///
+    /// The cursor to pass (as `cursor`) to get the next batch of items
+    ///
+    #[serde(default, deserialize_with = "utils::cursor::deserialize_option")]
+    pub next_cursor: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
-
     use crate::api::types::Creator;
     use futures::StreamExt;
 
-    use super::{paginated::Paginated, send_request, types::Model};
-
-    async fn creators(query: impl Into<Option<String>>) -> anyhow::Result<Paginated<Creator>> {
-        send_request(
-            "https://civitai.com/api/v1/creators",
-            query.into().into_iter().map(|q| ("query".to_string(), q)),
-        )
-        .await
+    use super::{fixtures, paginated::Paginated, CivitaiClient};
+
+    async fn creators(
+        client: &CivitaiClient,
+        query: impl Into<Option<String>>,
+    ) -> super::Result<Paginated<Creator>> {
+        client
+            .send_request(
+                client.endpoint("creators")?,
+                query.into().into_iter().map(|q| ("query".to_string(), q)),
+            )
+            .await
     }
 
     #[tokio::test]
     async fn test_creator_fetch() -> anyhow::Result<()> {
-        let c = creators("a".to_string()).await?;
-        let mut c = c.into_stream();
+        let client = fixtures::client();
+        let c = creators(&client, "a".to_string()).await?;
+        let mut c = c.into_stream(&client);
 
         let mut i = 0;
 
@@ -96,6 +98,9 @@ mod tests {
             println!("User({i}) => {user:?}");
         }
 
+        // Both pages, following `nextPage`.
+        assert_eq!(i, 3);
+
         Ok(())
     }
 }
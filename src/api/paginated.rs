@@ -1,21 +1,28 @@
 use std::{
-    borrow::BorrowMut,
-    ops::{Deref, DerefMut},
+    collections::{BTreeMap, VecDeque},
     pin::Pin,
-    task::Poll,
+    task::{ready, Context, Poll},
 };
 
 use futures::{future::BoxFuture, FutureExt, Stream};
-use serde::{de::DeserializeOwned, Deserialize};
+use reqwest::IntoUrl;
+use tokio::{sync::mpsc, task::JoinHandle};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 
-use crate::api::send_request;
+use crate::api::{CivitaiClient, Result};
 
 use super::PaginationMeta;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Paginated<T> {
     items: Vec<T>,
     metadata: PaginationMeta,
+
+    ///
+    /// Items which could not be decoded (only filled in by [Lenient]).
+    ///
+    #[serde(skip)]
+    errors: Vec<ItemError>,
 }
 
 impl<T> Paginated<T> {
@@ -23,93 +30,726 @@ impl<T> Paginated<T> {
         (self.items.iter(), &self.metadata)
     }
 
+    ///
+    /// The items left out of this page because they were malformed.
+    ///
+    pub fn errors(&self) -> &[ItemError] {
+        &self.errors
+    }
+
+    ///
+    /// The item at `position` in the page as it was sent,
+    /// `None` if that one was malformed (or there is no such position).
+    ///
+    pub fn at(&self, position: usize) -> Option<&T> {
+        let mut skipped = 0;
+
+        for err in &self.errors {
+            match err.index {
+                i if i == position => return None,
+                i if i < position => skipped += 1,
+                _ => {}
+            }
+        }
+
+        self.items.get(position - skipped)
+    }
+
+    ///
+    /// The number of items as sent, malformed ones included.
+    ///
+    fn len(&self) -> usize {
+        self.items.len() + self.errors.len()
+    }
+
     pub fn map<U>(self, mapper: impl Fn(T) -> U) -> Paginated<U> {
         let items = self.items.into_iter().map(mapper).collect();
 
         Paginated::<U> {
             items,
-            metadata: self.metadata
+            metadata: self.metadata,
+            errors: self.errors,
         }
     }
 }
 
-impl<'a, T: Clone + DeserializeOwned + 'a> Paginated<T> {
-    pub fn into_stream(self) -> PageIterator<'a, T> {
-        PageIterator {
-            meta: self.metadata,
-            current_item: 0,
-            items: self.items,
-            fut: None,
+///
+/// An item of a page which did not decode.
+///
+#[derive(Debug, thiserror::Error)]
+#[error("Could not decode item {index} at `{path}`: {error}")]
+pub struct ItemError {
+    ///
+    /// Position of the item in the page's `items`
+    ///
+    pub index: usize,
+
+    ///
+    /// Path of the failing field, relative to the item
+    ///
+    pub path: String,
+
+    pub raw: serde_json::Value,
+
+    #[source]
+    pub error: serde_json::Error,
+}
+
+///
+/// Decodes a page item by item, so one malformed item
+/// ends up in [Paginated::errors] instead of failing the whole page.
+///
+#[derive(Debug)]
+pub struct Lenient<T>(pub Paginated<T>);
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Lenient<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> std::result::Result<Self, D::Error> {
+        let page = Paginated::<serde_json::Value>::deserialize(de)?;
+
+        let mut items = Vec::with_capacity(page.items.len());
+        let mut errors = vec![];
+
+        for (index, raw) in page.items.into_iter().enumerate() {
+            match serde_path_to_error::deserialize::<_, T>(&raw) {
+                Ok(item) => items.push(item),
+                Err(err) => errors.push(ItemError {
+                    index,
+                    path: err.path().to_string(),
+                    error: err.into_inner(),
+                    raw,
+                }),
+            }
         }
+
+        Ok(Lenient(Paginated {
+            items,
+            metadata: page.metadata,
+            errors,
+        }))
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Paginated<T> {
+    ///
+    /// All items, from this page onwards (following `nextPage`).
+    ///
+    /// Pages which only have a `nextCursor` can not be continued from here,
+    /// as the original request is unknown: use [Endpoint::stream] for those.
+    ///
+    /// [Endpoint::stream]: super::endpoints::Endpoint::stream
+    ///
+    pub fn into_stream(self, client: &CivitaiClient) -> PageIterator<T> {
+        let mut stream = PageIterator::empty(client, None);
+        stream.advance(self);
+        stream
     }
 }
 
-impl<'a, T> Unpin for PageIterator<'a, T> {}
+type PageFuture<T> = BoxFuture<'static, Result<Paginated<T>>>;
+
+///
+/// The original request of a stream,
+/// repeated with a different `cursor` to get the next page.
+///
+#[derive(Clone)]
+struct Request {
+    path: String,
+    query: Vec<(String, String)>,
+}
 
-pub struct PageIterator<'a, T> {
-    meta: PaginationMeta,
-    current_item: usize,
+///
+/// Knows how to get from one page to the next.
+///
+#[derive(Clone)]
+struct Pager {
+    client: CivitaiClient,
+    request: Option<Request>,
 
-    items: Vec<T>,
-    fut: Option<BoxFuture<'a, anyhow::Result<Paginated<T>>>>,
-}
-
-impl<'a, T: Clone + DeserializeOwned + 'a> Stream for PageIterator<'a, T> {
-    type Item = T;
-
-    fn poll_next(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Option<Self::Item>> {
-        if self.current_item < self.items.len() {
-            let item = self.items.get(self.current_item).cloned().unwrap();
-            self.current_item += 1;
-            return Poll::Ready(Some(item));
-        }
-        
-        if self.fut.is_none() {
-            // Get the new page, if it exists.
-            if let Some(url) = self.meta.next_page.as_ref() {
-                self.fut = Some(
-                    send_request::<Paginated<T>>(
-                        url.clone(),
-                        []
-                    )
-                    .boxed(),
-                );
-            } else {
-                return Poll::Ready(None);
+    ///
+    /// Decode pages with [Lenient]
+    ///
+    lenient: bool,
+}
+
+impl Pager {
+    ///
+    /// Starts fetching the page after the one `meta` belongs to, if there is any.
+    ///
+    fn next<T: DeserializeOwned + Send + 'static>(
+        &self,
+        meta: &PaginationMeta,
+    ) -> Option<PageFuture<T>> {
+        if let Some(url) = meta.next_page.clone() {
+            return Some(self.follow(url));
+        }
+
+        let cursor = meta.next_cursor.clone()?;
+        self.fetch(&[("cursor", cursor)])
+    }
+
+    ///
+    /// Fetches the page at `url` (from `nextPage` or `prevPage`).
+    ///
+    fn follow<T: DeserializeOwned + Send + 'static>(&self, url: String) -> PageFuture<T> {
+        let client = self.client.clone();
+        let lenient = self.lenient;
+
+        async move { send(&client, url, vec![], lenient).await }.boxed()
+    }
+
+    ///
+    /// Repeats the original request, with the parameters in `set` replaced.
+    ///
+    fn fetch<T: DeserializeOwned + Send + 'static>(
+        &self,
+        set: &[(&str, String)],
+    ) -> Option<PageFuture<T>> {
+        let Request { path, query } = self.request.clone()?;
+
+        let client = self.client.clone();
+        let lenient = self.lenient;
+        let query = query
+            .into_iter()
+            .filter(|(k, _)| !set.iter().any(|(key, _)| key == k))
+            .chain(set.iter().map(|(k, v)| (k.to_string(), v.clone())))
+            .collect::<Vec<_>>();
+
+        Some(
+            async move {
+                let url = client.endpoint(&path)?;
+                send(&client, url, query, lenient).await
+            }
+            .boxed(),
+        )
+    }
+
+    fn has_next(&self, meta: &PaginationMeta) -> bool {
+        meta.next_page.is_some() || (meta.next_cursor.is_some() && self.request.is_some())
+    }
+}
+
+async fn send<T: DeserializeOwned>(
+    client: &CivitaiClient,
+    url: impl IntoUrl,
+    query: Vec<(String, String)>,
+    lenient: bool,
+) -> Result<Paginated<T>> {
+    if lenient {
+        let Lenient(page) = client.send_request(url, query).await?;
+        Ok(page)
+    } else {
+        client.send_request(url, query).await
+    }
+}
+
+///
+/// Whether `page` points to the same next page as `prev` did, without adding anything.
+/// Following it would never end.
+///
+fn is_stuck<T>(prev: Option<&PaginationMeta>, page: &Paginated<T>) -> bool {
+    let link = |m: &PaginationMeta| (m.next_page.clone(), m.next_cursor.clone());
+
+    page.items.is_empty() && prev.is_some_and(|prev| link(prev) == link(&page.metadata))
+}
+
+///
+/// Where the next page comes from.
+///
+enum Source<T> {
+    ///
+    /// Fetched once the current page runs out
+    ///
+    Lazy(Option<PageFuture<T>>),
+
+    ///
+    /// Fetched ahead of time by a background task
+    ///
+    Prefetch {
+        pages: mpsc::Receiver<Result<Paginated<T>>>,
+        task: JoinHandle<()>,
+    },
+}
+
+///
+/// A stream over the items of consecutive pages.
+///
+/// By default the next page is only fetched once the current one runs out,
+/// see [PageIterator::prefetch] to fetch ahead.
+/// If fetching a page fails, the error is yielded and the stream ends.
+///
+pub struct PageIterator<T> {
+    pager: Pager,
+    meta: Option<PaginationMeta>,
+
+    items: VecDeque<T>,
+
+    ///
+    /// How many items were taken out of the stream so far
+    ///
+    yielded: usize,
+
+    source: Source<T>,
+    done: bool,
+}
+
+impl<T> Unpin for PageIterator<T> {}
+
+impl<T> Drop for PageIterator<T> {
+    fn drop(&mut self) {
+        // Nobody is going to read the pages anymore.
+        if let Source::Prefetch { task, .. } = &self.source {
+            task.abort();
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> PageIterator<T> {
+    fn empty(client: &CivitaiClient, request: Option<Request>) -> Self {
+        Self {
+            pager: Pager {
+                client: client.clone(),
+                request,
+                lenient: false,
+            },
+            meta: None,
+            items: VecDeque::new(),
+            yielded: 0,
+            source: Source::Lazy(None),
+            done: false,
+        }
+    }
+
+    ///
+    /// All items of an endpoint, starting with the first page for `query`.
+    /// Follows `nextPage`, or failing that `nextCursor`.
+    ///
+    pub(crate) fn new(client: &CivitaiClient, path: String, query: Vec<(String, String)>) -> Self {
+        let mut stream = Self::empty(client, Some(Request { path, query }));
+        stream.source = Source::Lazy(stream.pager.fetch(&[]));
+        stream
+    }
+
+    ///
+    /// Fetch up to `depth` pages in the background,
+    /// while the current page's items are being consumed.
+    ///
+    /// Must be called from within a tokio runtime.
+    /// The background task is cancelled when the stream is dropped.
+    ///
+    pub fn prefetch(mut self, depth: usize) -> Self {
+        if depth == 0 || self.done {
+            return self;
+        }
+
+        let first = match &mut self.source {
+            Source::Lazy(fut) => fut
+                .take()
+                .or_else(|| self.meta.as_ref().and_then(|m| self.pager.next(m))),
+
+            // Already prefetching.
+            Source::Prefetch { .. } => return self,
+        };
+
+        let (tx, pages) = mpsc::channel(depth);
+        let pager = self.pager.clone();
+        let mut prev = self.meta.clone();
+
+        let task = tokio::spawn(async move {
+            let mut next = first;
+
+            while let Some(fut) = next.take() {
+                // Hold off until there is room for the page, so at most `depth` are fetched ahead.
+                let Ok(permit) = tx.reserve().await else {
+                    return;
+                };
+
+                let page = match fut.await {
+                    Ok(page) => page,
+                    Err(err) => {
+                        permit.send(Err(err));
+                        return;
+                    }
+                };
+
+                if !is_stuck(prev.as_ref(), &page) {
+                    next = pager.next(&page.metadata);
+                }
+
+                prev = Some(page.metadata.clone());
+                permit.send(Ok(page));
             }
+        });
+
+        self.source = Source::Prefetch { pages, task };
+        self
+    }
+
+    ///
+    /// The metadata of the last page taken from.
+    ///
+    pub fn meta(&self) -> Option<&PaginationMeta> {
+        self.meta.as_ref()
+    }
+
+    ///
+    /// Moves on to `page`.
+    ///
+    fn advance(&mut self, page: Paginated<T>) {
+        if is_stuck(self.meta.as_ref(), &page) {
+            self.done = true;
         }
 
+        self.items.extend(page.items);
+        self.meta = Some(page.metadata);
+    }
+
+    fn has_next(&self) -> bool {
+        self.meta.as_ref().is_some_and(|m| self.pager.has_next(m))
+    }
+
+    ///
+    /// Whether the first page (or the next, if lazy) is on its way.
+    ///
+    fn waiting(&self) -> bool {
+        match &self.source {
+            Source::Lazy(fut) => fut.is_some(),
+            Source::Prefetch { .. } => self.meta.is_none(),
+        }
+    }
+
+    fn poll_page(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Paginated<T>>>> {
+        match &mut self.source {
+            Source::Lazy(fut) => {
+                if fut.is_none() {
+                    *fut = self.meta.as_ref().and_then(|m| self.pager.next(m));
+                }
+
+                let Some(f) = fut.as_mut() else {
+                    return Poll::Ready(None);
+                };
 
-        // If we're already waiting for a new page to arrive, wait.
-        // If it's just arrived, we can update our internal state accordigly.
-        if let Some(ref mut fut) = self.fut {
-            let p = fut.poll_unpin(cx);
-            // println!("Polling result: {:?}", if p.is_pending() {
-            //     "Pending..."
-            // } else {
-            //     "Ready(T)"
-            // });
-            return p.map(|p| {
-                p.ok().map(|p| {
-                    self.current_item = 1;
-                    self.fut = None;
-                    self.items = p.items;
-                    self.meta = p.metadata;
+                let page = ready!(f.poll_unpin(cx));
+                *fut = None;
+                Poll::Ready(Some(page))
+            }
 
-                    self.items[0].clone()
-                })
-            });
+            Source::Prefetch { pages, .. } => pages.poll_recv(cx),
         }
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Stream for PageIterator<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.items.pop_front() {
+                self.yielded += 1;
+                return Poll::Ready(Some(Ok(item)));
+            }
 
+            if self.done {
+                return Poll::Ready(None);
+            }
 
-        // Else -- no pages left, return None and be done with it.
-        Poll::Ready(None)
+            match ready!(self.poll_page(cx)) {
+                // Empty pages are skipped over, by going round again.
+                Some(Ok(page)) => self.advance(page),
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                // No pages left.
+                None => {
+                    self.done = true;
+                    return Poll::Ready(None);
+                }
+            }
+        }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.meta.total_items, Some(self.meta.total_items))
+        let buffered = self.items.len();
+
+        if self.done || (!self.waiting() && !self.has_next()) {
+            return (buffered, Some(buffered));
+        }
+
+        // Cursor based endpoints do not always know their total.
+        match self.meta.as_ref().map(|m| m.total_items) {
+            Some(total) if total > 0 => {
+                let left = total.saturating_sub(self.yielded).max(buffered);
+                (buffered, Some(left))
+            }
+            _ => (buffered, None),
+        }
+    }
+}
+
+///
+/// Pages of an endpoint, fetched on demand and kept around,
+/// so items can be looked up by their absolute position in any order.
+///
+/// Pages are numbered from 1, the page of the original request.
+/// Nothing is fetched on its own: [PageCache::fetch] a page and [PageCache::insert] it
+/// (or [PageCache::load] it, which does both).
+///
+pub struct PageCache<T> {
+    pager: Pager,
+    pages: BTreeMap<usize, Paginated<T>>,
+
+    ///
+    /// The `page` the original request started at
+    ///
+    start: usize,
+}
+
+impl<T: DeserializeOwned + Send + 'static> PageCache<T> {
+    pub(crate) fn new(client: &CivitaiClient, path: String, query: Vec<(String, String)>) -> Self {
+        let start = query
+            .iter()
+            .find(|(k, _)| k == "page")
+            .and_then(|(_, v)| v.parse().ok())
+            .unwrap_or(1);
+
+        Self {
+            pager: Pager {
+                client: client.clone(),
+                request: Some(Request { path, query }),
+                lenient: false,
+            },
+            pages: BTreeMap::new(),
+            start,
+        }
+    }
+
+    ///
+    /// Set malformed items aside (see [Paginated::errors]) instead of failing their page.
+    ///
+    pub fn lenient(mut self) -> Self {
+        self.pager.lenient = true;
+        self
+    }
+
+    pub fn page(&self, k: usize) -> Option<&Paginated<T>> {
+        self.pages.get(&k)
+    }
+
+    ///
+    /// Any page's metadata, the first fetched page's if possible.
+    ///
+    pub fn meta(&self) -> Option<&PaginationMeta> {
+        self.pages.values().next().map(|p| &p.metadata)
+    }
+
+    ///
+    /// The number of items per page, once a page has been fetched.
+    ///
+    pub fn page_size(&self) -> Option<usize> {
+        match self.meta()?.page_size {
+            0 => self.pages.get(&1).map(Paginated::len),
+            size => Some(size),
+        }
+    }
+
+    ///
+    /// How many pages there are in total, if the endpoint says.
+    ///
+    pub fn total_pages(&self) -> Option<usize> {
+        let meta = self.meta()?;
+        Some(meta.total_pages).filter(|&n| n > 0 || meta.current_page > 0)
+    }
+
+    ///
+    /// How many positions the fetched pages cover, up to the end of the last one.
+    ///
+    pub fn loaded(&self) -> usize {
+        match (self.page_size(), self.pages.last_key_value()) {
+            (Some(size), Some((k, page))) => (k - 1) * size + page.len(),
+            _ => 0,
+        }
+    }
+
+    ///
+    /// Whether there are pages after the last one fetched.
+    ///
+    pub fn has_more(&self) -> bool {
+        self.pages
+            .values()
+            .next_back()
+            .is_some_and(|p| p.metadata.next_page.is_some() || p.metadata.next_cursor.is_some())
+    }
+
+    ///
+    /// The page which the item at absolute position `index` is on.
+    ///
+    pub fn page_of(&self, index: usize) -> Option<usize> {
+        match self.page_size()? {
+            0 => None,
+            size => Some(index / size + 1),
+        }
+    }
+
+    ///
+    /// The item at absolute position `index`, if its page is here.
+    ///
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let size = self.page_size()?;
+        let page = self.pages.get(&self.page_of(index)?)?;
+
+        page.at(index % size)
+    }
+
+    ///
+    /// Starts fetching page `k`, if it can be reached from what is here already:
+    /// endpoints with numbered pages can jump anywhere,
+    /// cursor based ones only to the page right after one already fetched.
+    ///
+    pub fn fetch(&self, k: usize) -> Option<PageFuture<T>> {
+        // `k` counts from the page the cache starts at, `totalPages` from the first.
+        if k == 0 || self.total_pages().is_some_and(|n| self.start + k - 1 > n) {
+            return None;
+        }
+
+        if k == 1 && self.pages.is_empty() {
+            return self.pager.fetch(&[]);
+        }
+
+        let before = self.pages.get(&(k - 1)).map(|p| &p.metadata);
+        let after = self.pages.get(&(k + 1)).map(|p| &p.metadata);
+
+        if let Some(url) = before.and_then(|m| m.next_page.clone()) {
+            return Some(self.pager.follow(url));
+        }
+
+        if let Some(url) = after.and_then(|m| m.prev_page.clone()) {
+            return Some(self.pager.follow(url));
+        }
+
+        if self.total_pages().is_some() {
+            return self.pager.fetch(&[("page", (self.start + k - 1).to_string())]);
+        }
+
+        self.pager.next(before?)
+    }
+
+    pub fn insert(&mut self, k: usize, page: Paginated<T>) {
+        self.pages.insert(k, page);
+    }
+
+    ///
+    /// Makes sure page `k` is here, fetching the pages leading up to it if needed.
+    /// Gives `None` if there is no such page.
+    ///
+    pub async fn load(&mut self, k: usize) -> Result<Option<&Paginated<T>>> {
+        while !self.pages.contains_key(&k) {
+            let (i, fut) = match self.fetch(k) {
+                Some(fut) => (k, fut),
+
+                // Walk towards `k` one page at a time.
+                None => {
+                    let i = self.pages.range(..k).next_back().map_or(1, |(last, _)| last + 1);
+
+                    match self.fetch(i) {
+                        Some(fut) => (i, fut),
+                        None => return Ok(None),
+                    }
+                }
+            };
+
+            let page = fut.await?;
+            self.insert(i, page);
+        }
+
+        Ok(self.pages.get(&k))
+    }
+
+    ///
+    /// The item at absolute position `index`, loading its page if needed.
+    ///
+    pub async fn get_or_load(&mut self, index: usize) -> Result<Option<&T>> {
+        if self.page_size().is_none() {
+            self.load(1).await?;
+        }
+
+        match self.page_of(index) {
+            Some(k) => {
+                self.load(k).await?;
+                Ok(self.get(index))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::api::{
+        endpoints::{
+            models::{models, Params},
+            Endpoint,
+        },
+        fixtures,
+    };
+
+    use super::Lenient;
+
+    #[derive(Debug, Deserialize)]
+    struct Item {
+        id: usize,
+    }
+
+    #[test]
+    fn test_lenient() -> anyhow::Result<()> {
+        let body = r#"{
+            "items": [{ "id": 1 }, { "id": "two" }, { "id": 3 }],
+            "metadata": { "totalItems": 3 }
+        }"#;
+
+        assert!(serde_json::from_str::<super::Paginated<Item>>(body).is_err());
+
+        let Lenient(page) = serde_json::from_str::<Lenient<Item>>(body)?;
+        let ids = page.first_few().0.map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 3]);
+
+        let [err] = page.errors() else {
+            panic!("Expected exactly one error, got {:?}", page.errors());
+        };
+        assert_eq!(err.index, 1);
+        assert_eq!(err.path, "id");
+        assert_eq!(err.raw["id"], "two");
+
+        // Positions are the ones sent, not shifted by the malformed item.
+        assert_eq!(page.at(0).map(|i| i.id), Some(1));
+        assert!(page.at(1).is_none());
+        assert_eq!(page.at(2).map(|i| i.id), Some(3));
+        assert!(page.at(3).is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_page_cache_start() -> anyhow::Result<()> {
+        let client = fixtures::client();
+
+        // Starting at the last of two pages, so there is nothing after the first.
+        let mut pages = models::pages(
+            &client,
+            Params {
+                query: "happy".to_string().into(),
+                page: 2.into(),
+                ..Default::default()
+            },
+        );
+
+        let first = pages.load(1).await?.expect("The starting page");
+        assert_eq!(first.metadata.current_page, 2);
+
+        assert!(pages.fetch(2).is_none());
+        assert!(pages.load(2).await?.is_none());
+
+        Ok(())
     }
 }
@@ -0,0 +1,262 @@
+//!
+//! Opt-in recording of responses to disk,
+//! which can be replayed later instead of hitting the network.
+//!
+
+use std::{fs, path::PathBuf};
+
+use reqwest::Url;
+
+use super::error::{ApiError, Result};
+
+///
+/// Keys longer than this get shortened (before their hash).
+///
+const MAX_KEY_LEN: usize = 150;
+
+///
+/// Query parameters which never make it into a file name.
+///
+const SECRET_PARAMS: &[&str] = &["token", "apiKey"];
+
+const REDACTED: &str = "REDACTED";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    ///
+    /// Send requests as usual, and write every successful response to disk.
+    ///
+    #[default]
+    Record,
+
+    ///
+    /// Never touch the network, only serve previously recorded responses.
+    ///
+    Replay,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    ///
+    /// The directory responses are written to, or read from.
+    ///
+    pub dir: PathBuf,
+
+    pub mode: Mode,
+
+    ///
+    /// Strings scrubbed from recorded bodies, on top of the client's API key.
+    ///
+    pub redact: Vec<String>,
+}
+
+impl Recorder {
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: Mode::Record,
+            redact: Vec::new(),
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: Mode::Replay,
+            redact: Vec::new(),
+        }
+    }
+
+    ///
+    /// The file name a response is stored under.
+    ///
+    /// Endpoint urls (those under `base`) are named after their path relative to it,
+    /// anything else (like images) after its host and path.
+    /// The query parameters are appended sorted, so the parameter order does not matter.
+    /// As that name can be the same for different urls (`models/1` and `models_1`),
+    /// it ends with a short hash of the path and parameters as they were.
+    ///
+    /// e.g. `https://civitai.com/api/v1/models?query=happy&limit=3`
+    /// becomes `models.limit-3.query-happy.1d2f9a3e.json`.
+    ///
+    pub fn key(base: &Url, url: &Url, json: bool) -> String {
+        let same_origin = url.origin() == base.origin();
+        let path = match url.path().strip_prefix(base.path()) {
+            Some(rest) if same_origin => rest,
+            _ => url.path(),
+        };
+
+        let mut parts = vec![];
+
+        if !same_origin {
+            parts.extend(url.host_str().map(str::to_string));
+        }
+
+        parts.extend(
+            path.split('/')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+        );
+
+        let mut pairs = url
+            .query_pairs()
+            .filter(|(k, _)| !SECRET_PARAMS.contains(&k.as_ref()))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        parts.extend(pairs.iter().map(|(k, v)| format!("{k}-{v}")));
+
+        // Each field prefixed with its length, so no two urls give the same one.
+        let host = if same_origin { "" } else { url.host_str().unwrap_or_default() };
+        let raw = [host, path]
+            .into_iter()
+            .chain(pairs.iter().flat_map(|(k, v)| [k.as_ref(), v.as_ref()]))
+            .map(|field| format!("{}:{field}", field.len()))
+            .collect::<String>();
+
+        // Files keep their extension (like `.jpeg`) last, after the hash.
+        let ext = match parts.last_mut() {
+            _ if json => Some("json".to_string()),
+            Some(last) if pairs.is_empty() => {
+                let split = last
+                    .rsplit_once('.')
+                    .map(|(stem, ext)| (stem.to_string(), ext.to_string()));
+
+                split.map(|(stem, ext)| {
+                    *last = stem;
+                    ext
+                })
+            }
+            _ => None,
+        };
+
+        let mut key = sanitize(&parts.join("."));
+        key.truncate(MAX_KEY_LEN - ".00000000".len());
+        key = format!("{key}.{:08x}", fnv1a(raw.as_bytes()) as u32);
+
+        if let Some(ext) = ext {
+            key = format!("{key}.{}", sanitize(&ext));
+        }
+
+        key
+    }
+
+    pub(crate) fn save(&self, key: &str, body: &[u8], secrets: &[&str]) -> Result<()> {
+        let path = self.dir.join(key);
+
+        // Only text can sensibly be redacted, images are written as-is.
+        let body = match std::str::from_utf8(body) {
+            Ok(txt) => secrets
+                .iter()
+                .copied()
+                .chain(self.redact.iter().map(String::as_str))
+                .filter(|s| !s.is_empty())
+                .fold(txt.to_string(), |txt, secret| txt.replace(secret, REDACTED))
+                .into_bytes(),
+            Err(_) => body.to_vec(),
+        };
+
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, body))
+            .map_err(|source| ApiError::Recording { path, source })
+    }
+
+    pub(crate) fn load(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.dir.join(key);
+
+        fs::read(&path).map_err(|source| ApiError::Recording { path, source })
+    }
+}
+
+fn sanitize(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+///
+/// Stable across builds and platforms, unlike `DefaultHasher`.
+///
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::Recorder;
+
+    #[test]
+    fn test_key() -> anyhow::Result<()> {
+        let base = Url::parse("https://civitai.com/api/v1/")?;
+
+        assert_eq!(
+            Recorder::key(
+                &base,
+                &Url::parse("https://civitai.com/api/v1/models?query=happy&limit=3")?,
+                true
+            ),
+            "models.limit-3.query-happy.845e11d7.json"
+        );
+
+        assert_eq!(
+            Recorder::key(
+                &base,
+                &Url::parse("https://civitai.com/api/v1/models?limit=3&query=happy&token=abc")?,
+                true
+            ),
+            "models.limit-3.query-happy.845e11d7.json"
+        );
+
+        assert_eq!(
+            Recorder::key(
+                &base,
+                &Url::parse("https://image.civitai.com/abc/width=450/1234.jpeg")?,
+                false
+            ),
+            "image.civitai.com.abc.width_450.1234.a93fc505.jpeg"
+        );
+
+        // Urls which only differ in what does not make it into a file name.
+        let key = |url: &str| Recorder::key(&base, &Url::parse(url).unwrap(), true);
+        assert_ne!(
+            key("https://civitai.com/api/v1/models/limit-3"),
+            key("https://civitai.com/api/v1/models?limit=3")
+        );
+        assert_ne!(
+            key("https://civitai.com/api/v1/models?query=a/b"),
+            key("https://civitai.com/api/v1/models?query=a_b")
+        );
+
+        let long = Recorder::key(
+            &base,
+            &Url::parse(&format!("https://civitai.com/api/v1/models?query={}", "a".repeat(400)))?,
+            true,
+        );
+        assert!(long.len() <= super::MAX_KEY_LEN + ".json".len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_redacts() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("civitai-recorder-{}", std::process::id()));
+        let mut rec = Recorder::record(&dir);
+        rec.redact.push("hunter2".to_string());
+
+        rec.save("creators.json", br#"{"key":"secret-key","pw":"hunter2"}"#, &["secret-key"])?;
+
+        assert_eq!(
+            String::from_utf8(rec.load("creators.json")?)?,
+            r#"{"key":"REDACTED","pw":"REDACTED"}"#
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
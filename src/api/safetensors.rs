@@ -0,0 +1,329 @@
+//!
+//! Reading the header of a local `.safetensors` file, without loading its weights.
+//!
+//! The file starts with the length of the header (a little-endian `u64`),
+//! then the header itself: JSON with the dtype, shape and offsets of each tensor,
+//! and free-form `__metadata__` (where trainers put their parameters).
+//!
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    types::model::{FileMetadata, FloatingPoint, Format, Type},
+    utils::open_enum,
+};
+
+///
+/// The format caps headers at 100MB, anything longer is not a safetensors file.
+///
+pub const MAX_HEADER_LEN: u64 = 100_000_000;
+
+const METADATA: &str = "__metadata__";
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeaderError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("Header of {0} bytes is too long")]
+    TooLong(u64),
+
+    #[error("Invalid header: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+open_enum! {
+    pub enum Dtype {
+        Bool = "BOOL",
+        U8 = "U8",
+        I8 = "I8",
+        I16 = "I16",
+        I32 = "I32",
+        I64 = "I64",
+        F8E4M3 = "F8_E4M3",
+        F8E5M2 = "F8_E5M2",
+        F16 = "F16",
+        Bf16 = "BF16",
+        F32 = "F32",
+        F64 = "F64",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TensorInfo {
+    pub dtype: Dtype,
+    pub shape: Vec<u64>,
+
+    ///
+    /// Start and end of the tensor's data, from the end of the header
+    ///
+    pub data_offsets: (u64, u64),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Header {
+    ///
+    /// By name
+    ///
+    pub tensors: BTreeMap<String, TensorInfo>,
+
+    ///
+    /// Free-form (like kohya's `ss_*` training parameters)
+    ///
+    pub metadata: HashMap<String, String>,
+}
+
+impl TensorInfo {
+    ///
+    /// The number of values in the tensor
+    ///
+    pub fn len(&self) -> u64 {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Dtype {
+    pub fn floating_point(&self) -> Option<FloatingPoint> {
+        match self {
+            Dtype::F8E4M3 | Dtype::F8E5M2 => Some(FloatingPoint::Fp8),
+            Dtype::F16 => Some(FloatingPoint::Fp16),
+            Dtype::Bf16 => Some(FloatingPoint::Bf16),
+            Dtype::F32 => Some(FloatingPoint::Fp32),
+            _ => None,
+        }
+    }
+}
+
+impl Header {
+    ///
+    /// Reads just the header of the file at `path`.
+    ///
+    pub fn read(path: &Path) -> Result<Self, HeaderError> {
+        let mut file = fs::File::open(path)?;
+
+        let mut len = [0; 8];
+        file.read_exact(&mut len)?;
+
+        let len = u64::from_le_bytes(len);
+        if len > MAX_HEADER_LEN {
+            return Err(HeaderError::TooLong(len));
+        }
+
+        // The length is only what the file claims, so the buffer grows with what is there.
+        let mut raw = vec![];
+        file.take(len).read_to_end(&mut raw)?;
+
+        if (raw.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Self::parse(&raw)
+    }
+
+    ///
+    /// Parses the JSON of a header.
+    ///
+    pub fn parse(raw: &[u8]) -> Result<Self, HeaderError> {
+        let mut tensors = serde_json::from_slice::<BTreeMap<String, serde_json::Value>>(raw)?;
+
+        let metadata = match tensors.remove(METADATA) {
+            Some(metadata) => serde_json::from_value(metadata)?,
+            None => HashMap::new(),
+        };
+
+        let tensors = tensors
+            .into_iter()
+            .map(|(name, info)| Ok((name, serde_json::from_value(info)?)))
+            .collect::<Result<_, serde_json::Error>>()?;
+
+        Ok(Self { tensors, metadata })
+    }
+
+    ///
+    /// The number of values in all tensors
+    ///
+    pub fn parameters(&self) -> u64 {
+        self.tensors.values().map(TensorInfo::len).sum()
+    }
+
+    ///
+    /// The floating point most of the weights are in.
+    ///
+    pub fn precision(&self) -> Option<FloatingPoint> {
+        let mut counts = HashMap::<FloatingPoint, u64>::new();
+
+        for tensor in self.tensors.values() {
+            if let Some(fp) = tensor.dtype.floating_point() {
+                *counts.entry(fp).or_default() += tensor.len();
+            }
+        }
+
+        counts.into_iter().max_by_key(|(_, n)| *n).map(|(fp, _)| fp)
+    }
+
+    ///
+    /// What kind of model this is, going by how its tensors are named
+    /// (and what the trainer said, for LoRAs).
+    ///
+    pub fn model_type(&self) -> Option<Type> {
+        let any = |f: &dyn Fn(&str) -> bool| self.tensors.keys().any(|k| f(k));
+        let all = |f: &dyn Fn(&str) -> bool| {
+            !self.tensors.is_empty() && self.tensors.keys().all(|k| f(k))
+        };
+
+        let network = self
+            .metadata
+            .get("ss_network_module")
+            .map(String::as_str)
+            .unwrap_or_default();
+
+        if any(&|k| k.ends_with(".dora_scale")) {
+            return Some(Type::DoRA);
+        }
+
+        if network.contains("lycoris")
+            || any(&|k| k.contains(".hada_w1") || k.contains(".lokr_w1"))
+        {
+            return Some(Type::LoCon);
+        }
+
+        if network.contains("lora")
+            || any(&|k| k.contains("lora_down") || k.contains("lora_up") || k.contains(".lora_A"))
+        {
+            return Some(Type::Lora);
+        }
+
+        if any(&|k| k.starts_with("control_model.") || k.contains("input_hint_block")) {
+            return Some(Type::Controlnet);
+        }
+
+        if any(&|k| k.contains("motion_modules.")) {
+            return Some(Type::MotionModule);
+        }
+
+        if any(&|k| k.starts_with("model.diffusion_model.")) {
+            return Some(Type::Checkpoint);
+        }
+
+        if all(&|k| {
+            k.starts_with("encoder.")
+                || k.starts_with("decoder.")
+                || k.starts_with("quant_conv.")
+                || k.starts_with("post_quant_conv.")
+        }) {
+            return Some(Type::Vae);
+        }
+
+        // Embeddings are a handful of vectors, by model (`clip_l`, `clip_g`) or under one key.
+        if all(&|k| {
+            matches!(k, "emb_params" | "clip_l" | "clip_g") || k.starts_with("string_to_param")
+        }) {
+            return Some(Type::TextualInversion);
+        }
+
+        None
+    }
+
+    ///
+    /// What the header says about the file, in the terms Civitai uses,
+    /// to put next to the API's [FileMetadata].
+    ///
+    pub fn file_metadata(&self) -> FileMetadata {
+        FileMetadata {
+            fp: self.precision(),
+            size: None,
+            format: Some(Format::SafeTensor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::api::types::model::{FloatingPoint, Type};
+
+    use super::{Dtype, Header, HeaderError, MAX_HEADER_LEN};
+
+    fn header(value: serde_json::Value) -> Header {
+        Header::parse(value.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_read() -> anyhow::Result<()> {
+        let raw = json!({
+            "__metadata__": { "ss_network_module": "networks.lora", "ss_num_epochs": "10" },
+            "lora_unet_down.alpha": { "dtype": "F32", "shape": [], "data_offsets": [0, 4] },
+            "lora_unet_down.lora_down.weight": {
+                "dtype": "F16", "shape": [4, 320], "data_offsets": [4, 2564]
+            },
+        })
+        .to_string();
+
+        let path = std::env::temp_dir().join(format!("civitai-header-{}", std::process::id()));
+        let mut file = (raw.len() as u64).to_le_bytes().to_vec();
+        file.extend(raw.as_bytes());
+        file.extend([0; 2564]);
+        std::fs::write(&path, file)?;
+
+        let header = Header::read(&path)?;
+        assert_eq!(header.tensors.len(), 2);
+        assert_eq!(header.metadata["ss_num_epochs"], "10");
+
+        let down = &header.tensors["lora_unet_down.lora_down.weight"];
+        assert_eq!(down.dtype, Dtype::F16);
+        assert_eq!(down.data_offsets, (4, 2564));
+        assert_eq!(header.parameters(), 1 + 4 * 320);
+
+        assert_eq!(header.precision(), Some(FloatingPoint::Fp16));
+        assert_eq!(header.model_type(), Some(Type::Lora));
+
+        // Not a safetensors file.
+        std::fs::write(&path, u64::MAX.to_le_bytes())?;
+        assert!(matches!(Header::read(&path), Err(HeaderError::TooLong(_))));
+
+        // Claims a longer header than there is.
+        std::fs::write(&path, [&MAX_HEADER_LEN.to_le_bytes()[..], b"{}"].concat())?;
+        assert!(matches!(Header::read(&path), Err(HeaderError::Io(_))));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_type() {
+        let tensor = json!({ "dtype": "BF16", "shape": [1], "data_offsets": [0, 2] });
+
+        let checkpoint = header(json!({
+            "model.diffusion_model.input_blocks.0.0.weight": tensor,
+            "first_stage_model.decoder.conv_in.weight": tensor,
+        }));
+        assert_eq!(checkpoint.model_type(), Some(Type::Checkpoint));
+        assert_eq!(checkpoint.precision(), Some(FloatingPoint::Bf16));
+
+        let vae = header(json!({ "encoder.conv_in.weight": tensor, "decoder.conv_in.weight": tensor }));
+        assert_eq!(vae.model_type(), Some(Type::Vae));
+
+        let embedding = header(json!({ "clip_l": tensor, "clip_g": tensor }));
+        assert_eq!(embedding.model_type(), Some(Type::TextualInversion));
+
+        let dora = header(json!({
+            "lora_unet_a.lora_down.weight": tensor,
+            "lora_unet_a.dora_scale": tensor,
+        }));
+        assert_eq!(dora.model_type(), Some(Type::DoRA));
+
+        assert_eq!(header(json!({ "something.else": tensor })).model_type(), None);
+    }
+}
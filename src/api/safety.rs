@@ -0,0 +1,212 @@
+//!
+//! Deciding whether a model file is safe to download, from Civitai's scans of it.
+//!
+
+use std::{fmt, path::Path};
+
+use super::types::model::{File, Format, ScanResult};
+
+///
+/// Extensions of files which are (most likely) pickles, for files without a format.
+///
+const PICKLE_EXTENSIONS: [&str; 5] = ["ckpt", "pt", "pth", "bin", "pkl"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scan {
+    Pickle,
+    Virus,
+}
+
+impl fmt::Display for Scan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scan::Pickle => "Pickle",
+            Scan::Virus => "Virus",
+        })
+    }
+}
+
+///
+/// Why a file is not downloaded.
+///
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Unsafe {
+    ///
+    /// A scan found something, failed or has not finished (or never ran).
+    ///
+    #[error(
+        "{scan} scan: {}{}",
+        result.as_ref().map_or("Not scanned", ScanResult::as_str),
+        message.as_ref().map(|m| format!(" ({m})")).unwrap_or_default()
+    )]
+    Scan {
+        scan: Scan,
+        result: Option<ScanResult>,
+        message: Option<String>,
+    },
+
+    ///
+    /// The file passed its scans, but is a pickle, which can run code when it is loaded.
+    ///
+    #[error("{0} is a pickle, which can run code when loaded")]
+    Pickle(String),
+}
+
+///
+/// What may be downloaded.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Policy {
+    ///
+    /// Download pickles which passed their scans.
+    /// Otherwise only safer formats (like SafeTensor) are.
+    ///
+    pub allow_pickle: bool,
+}
+
+impl Policy {
+    ///
+    /// Everything which passed its scans.
+    ///
+    pub fn allow_pickle() -> Self {
+        Self { allow_pickle: true }
+    }
+
+    pub fn check(&self, file: &File) -> Result<(), Unsafe> {
+        for (scan, result, message) in scans(file) {
+            if result != Some(&ScanResult::Success) {
+                return Err(Unsafe::Scan {
+                    scan,
+                    result: result.cloned(),
+                    message: message.map(str::to_string),
+                });
+            }
+        }
+
+        if is_pickle(file) && !self.allow_pickle {
+            return Err(Unsafe::Pickle(file.name.clone()));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// The file to download out of `files` (those of a version):
+    /// SafeTensors before anything else, then the primary file.
+    ///
+    pub fn pick<'a>(&self, files: &'a [File]) -> Option<&'a File> {
+        files
+            .iter()
+            .filter(|file| self.check(file).is_ok())
+            .min_by_key(|file| {
+                (
+                    format(file) != Some(&Format::SafeTensor),
+                    file.primary != Some(true),
+                )
+            })
+    }
+}
+
+///
+/// The result of each scan of `file`, with its message.
+///
+pub fn scans(file: &File) -> [(Scan, Option<&ScanResult>, Option<&str>); 2] {
+    [
+        (
+            Scan::Pickle,
+            file.pickle_scan_result.as_ref(),
+            file.pickle_scan_message.as_deref(),
+        ),
+        (
+            Scan::Virus,
+            file.virus_scan_result.as_ref(),
+            file.virus_scan_message.as_deref(),
+        ),
+    ]
+}
+
+///
+/// Whether loading `file` may run code from it.
+///
+pub fn is_pickle(file: &File) -> bool {
+    match format(file) {
+        Some(Format::PickleTensor) => true,
+        Some(Format::Other) | None => Path::new(&file.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PICKLE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())),
+        Some(_) => false,
+    }
+}
+
+fn format(file: &File) -> Option<&Format> {
+    file.metadata.as_ref()?.format.as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::api::types::model::{File, ScanResult};
+
+    use super::{Policy, Scan, Unsafe};
+
+    fn file(id: usize, name: &str, format: &str, pickle: &str, primary: bool) -> File {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": name,
+            "metadata": { "format": format },
+            "pickleScanResult": pickle,
+            "pickleScanMessage": "No Pickle imports",
+            "virusScanResult": "Success",
+            "scannedAt": null,
+            "hashes": {},
+            "primary": primary,
+            "downloadUrl": format!("https://civitai.com/api/download/models/{id}"),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = Policy::default();
+
+        assert!(policy
+            .check(&file(1, "a.safetensors", "SafeTensor", "Success", true))
+            .is_ok());
+
+        assert_eq!(
+            policy.check(&file(2, "a.safetensors", "SafeTensor", "Danger", true)),
+            Err(Unsafe::Scan {
+                scan: Scan::Pickle,
+                result: Some(ScanResult::Danger),
+                message: Some("No Pickle imports".to_string()),
+            })
+        );
+        assert!(policy
+            .check(&file(3, "a.safetensors", "SafeTensor", "Pending", true))
+            .is_err());
+
+        // Pickles only with the override, and never when dangerous.
+        let pickle = file(4, "a.ckpt", "Other", "Success", true);
+        assert_eq!(policy.check(&pickle), Err(Unsafe::Pickle("a.ckpt".to_string())));
+        assert!(Policy::allow_pickle().check(&pickle).is_ok());
+        assert!(Policy::allow_pickle()
+            .check(&file(5, "a.pt", "PickleTensor", "Error", true))
+            .is_err());
+    }
+
+    #[test]
+    fn test_pick() {
+        let files = [
+            file(1, "a.ckpt", "PickleTensor", "Success", true),
+            file(2, "a-danger.safetensors", "SafeTensor", "Danger", false),
+            file(3, "a.safetensors", "SafeTensor", "Success", false),
+        ];
+
+        assert_eq!(Policy::default().pick(&files).map(|f| f.id), Some(3));
+        assert_eq!(Policy::allow_pickle().pick(&files).map(|f| f.id), Some(3));
+        assert_eq!(Policy::allow_pickle().pick(&files[..2]).map(|f| f.id), Some(1));
+        assert!(Policy::default().pick(&files[..2]).is_none());
+    }
+}
@@ -0,0 +1,251 @@
+//!
+//! Files written next to a downloaded model, in the layout popular web UIs read:
+//! `<name>.civitai.info` (the version, as the API has it), `<name>.preview.png`
+//! and `<name>.json` (trigger words and notes), so they can show previews
+//! and trigger words without asking the API.
+//!
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use super::{
+    types::{
+        model::{ParentModel, Version},
+        Model,
+    },
+    utils::strip_html,
+    ApiError, CivitaiClient,
+};
+
+pub const INFO_SUFFIX: &str = ".civitai.info";
+pub const PREVIEW_SUFFIX: &str = ".preview.png";
+pub const JSON_SUFFIX: &str = ".json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SidecarError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+
+    #[error("Writing {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: io::Error,
+    },
+}
+
+type Result<T> = std::result::Result<T, SidecarError>;
+
+///
+/// Where the sidecars of a model file go.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    pub info: PathBuf,
+    pub preview: PathBuf,
+    pub json: PathBuf,
+}
+
+impl Paths {
+    ///
+    /// Next to `model`, named after it without its extension.
+    ///
+    pub fn of(model: &Path) -> Self {
+        let stem = model.with_extension("").into_os_string();
+        let with = |suffix: &str| {
+            let mut path = stem.clone();
+            path.push(suffix);
+            PathBuf::from(path)
+        };
+
+        Self {
+            info: with(INFO_SUFFIX),
+            preview: with(PREVIEW_SUFFIX),
+            json: with(JSON_SUFFIX),
+        }
+    }
+}
+
+///
+/// What the sidecars of a downloaded file are written from.
+///
+#[derive(Debug, Clone)]
+pub struct Sidecar {
+    pub version: Version,
+
+    ///
+    /// For the description, and to name the model when the version does not
+    ///
+    pub model: Option<Model>,
+}
+
+///
+/// The `<name>.json` A1111 keeps per model ("user metadata").
+///
+#[derive(Debug, Serialize)]
+struct UserMetadata {
+    description: String,
+
+    #[serde(rename = "activation text")]
+    activation_text: String,
+
+    #[serde(rename = "preferred weight")]
+    preferred_weight: f64,
+
+    notes: String,
+}
+
+impl Sidecar {
+    pub fn new(version: Version, model: Option<Model>) -> Self {
+        Self { version, model }
+    }
+
+    ///
+    /// Writes the sidecars of the model file at `path`.
+    /// The preview is the first of the version's images which can be decoded
+    /// (there is none if all of them are videos).
+    ///
+    pub async fn write(&self, client: &CivitaiClient, path: &Path) -> Result<Paths> {
+        let paths = Paths::of(path);
+
+        let mut version = self.version.clone();
+        if let (None, Some(model)) = (&version.model, &self.model) {
+            version.model = Some(ParentModel {
+                name: model.name.clone(),
+                _type: model._type.clone(),
+                nsfw: false,
+                poi: false,
+            });
+        }
+        write_json(&paths.info, &version).await?;
+
+        let description = self
+            .model
+            .as_ref()
+            .map(|m| m.description.as_str())
+            .or(version.description.as_deref())
+            .map(strip_html)
+            .unwrap_or_default();
+
+        let notes = version
+            .model_id
+            .and_then(|id| {
+                let page = format!("/models/{id}?modelVersionId={}", version.id);
+                client.base_url().join(&page).ok()
+            })
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+
+        let user = UserMetadata {
+            description,
+            activation_text: version.trained_words.join(", "),
+            preferred_weight: 0.0,
+            notes,
+        };
+        write_json(&paths.json, &user).await?;
+
+        self.write_preview(client, &paths.preview).await?;
+
+        Ok(paths)
+    }
+
+    async fn write_preview(&self, client: &CivitaiClient, path: &Path) -> Result<()> {
+        let images = self.version.get_images(client).await?;
+
+        let (images, _) = images.first_few();
+
+        for image in images {
+            let bytes = client.fetch_bytes(&image.url).await?;
+
+            let saved = {
+                let path = path.to_path_buf();
+                tokio::task::spawn_blocking(move || {
+                    image::load_from_memory(&bytes)?
+                        .save_with_format(&path, image::ImageFormat::Png)
+                })
+            }
+            .await
+            .expect("Encoding panicked!");
+
+            match saved {
+                Ok(()) => return Ok(()),
+                Err(image::ImageError::IoError(source)) => {
+                    return Err(SidecarError::Io {
+                        path: path.to_path_buf(),
+                        source,
+                    })
+                }
+                // Not a picture (like a video), try the next one.
+                Err(_) => continue,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let io_error = |source| SidecarError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let raw = serde_json::to_vec_pretty(value).map_err(|err| io_error(io::Error::other(err)))?;
+    tokio::fs::write(path, raw).await.map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::api::{
+        endpoints::{model::model, model_versions, Endpoint},
+        fixtures,
+    };
+
+    use super::{Paths, Sidecar};
+
+    #[test]
+    fn test_paths() {
+        let paths = Paths::of(Path::new("/models/happy.v2.safetensors"));
+        assert_eq!(paths.info, Path::new("/models/happy.v2.civitai.info"));
+        assert_eq!(paths.preview, Path::new("/models/happy.v2.preview.png"));
+        assert_eq!(paths.json, Path::new("/models/happy.v2.json"));
+    }
+
+    #[tokio::test]
+    async fn test_write() -> anyhow::Result<()> {
+        let client = fixtures::client();
+        let mut version = model_versions::by_id::get(&client, 10011.into()).await?;
+        version.model = None;
+        let parent = model::get(&client, 1001.into()).await?;
+
+        let dir = std::env::temp_dir().join(format!("civitai-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+
+        let sidecar = Sidecar::new(version.clone(), Some(parent));
+        let paths = sidecar.write(&client, &dir.join("happy.safetensors")).await?;
+
+        let info: serde_json::Value = serde_json::from_slice(&std::fs::read(&paths.info)?)?;
+        assert_eq!(info["id"], 10011);
+        assert_eq!(info["model"]["name"], "Happy Checkpoint");
+
+        let user: serde_json::Value = serde_json::from_slice(&std::fs::read(&paths.json)?)?;
+        assert_eq!(user["activation text"], version.trained_words.join(", "));
+        assert!(user["notes"]
+            .as_str()
+            .unwrap()
+            .ends_with("/models/1001?modelVersionId=10011"));
+
+        let preview = image::open(&paths.preview)?;
+        assert_eq!((preview.width(), preview.height()), (4, 6));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//!
+//! Keeping the request rate polite, and riding out transient failures.
+//!
+
+use std::time::Duration;
+
+use reqwest::{header, Response, StatusCode};
+use tokio::{sync::Mutex, time::Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    ///
+    /// Requests allowed per second, on average
+    ///
+    pub per_second: f64,
+
+    ///
+    /// Requests which may go out at once, after a quiet spell
+    ///
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_second: 2.0,
+            burst: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    ///
+    /// How many times a failed request is sent again (0 never retries)
+    ///
+    pub max_retries: u32,
+
+    ///
+    /// The delay before the first retry, doubled for each one after
+    ///
+    pub base_delay: Duration,
+
+    ///
+    /// The longest the client will wait before a retry.
+    /// A `Retry-After` longer than this is not waited for.
+    ///
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    ///
+    /// Never retry.
+    ///
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// How long to wait before retry number `attempt` (from 0),
+    /// or `None` to give up.
+    ///
+    /// Without a `retry_after` from the server, this is exponential backoff with full jitter.
+    ///
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        if let Some(wait) = retry_after {
+            return Some(wait).filter(|&w| w <= self.max_delay);
+        }
+
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        Some(ceiling.mul_f64(fastrand::f64()))
+    }
+}
+
+///
+/// Whether a request which failed with `status` is worth sending again.
+///
+pub(crate) fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+///
+/// The `Retry-After` of a 429 or 503, either in seconds or as a date.
+///
+pub(crate) fn retry_after(res: &Response) -> Option<Duration> {
+    if !matches!(
+        res.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+
+    parse_retry_after(res.headers().get(header::RETRY_AFTER)?.to_str().ok()?)
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.signed_duration_since(chrono::Utc::now());
+
+    // A date in the past means "now".
+    Some(wait.to_std().unwrap_or_default())
+}
+
+///
+/// A token bucket, shared by everything going through one client.
+///
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    limit: RateLimit,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(Bucket {
+                tokens: limit.burst as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    ///
+    /// Waits until a request may go out.
+    ///
+    pub(crate) async fn acquire(&self) {
+        let RateLimit { per_second, burst } = self.limit;
+
+        if per_second <= 0.0 {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut bucket = self.state.lock().await;
+
+                let now = Instant::now();
+                let earned = now.duration_since(bucket.refilled).as_secs_f64() * per_second;
+                bucket.tokens = (bucket.tokens + earned).min(burst.max(1) as f64);
+                bucket.refilled = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / per_second)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{parse_retry_after, Limiter, RateLimit, RetryPolicy};
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(250),
+        };
+
+        for _ in 0..100 {
+            assert!(policy.delay(0, None).unwrap() <= Duration::from_millis(100));
+            assert!(policy.delay(2, None).unwrap() <= Duration::from_millis(250));
+        }
+
+        assert!(policy.delay(3, None).is_none());
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_millis(200))),
+            Some(Duration::from_millis(200))
+        );
+        assert!(policy.delay(0, Some(Duration::from_secs(60))).is_none());
+    }
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert!(parse_retry_after("soon").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_limiter() {
+        let limiter = Limiter::new(RateLimit {
+            per_second: 50.0,
+            burst: 2,
+        });
+
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+
+        // The burst goes out at once, the other two wait 20ms each.
+        assert!(start.elapsed() >= Duration::from_millis(35));
+    }
+}
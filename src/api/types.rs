@@ -1,9 +1,9 @@
 use futures::Future;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{Paginated, endpoints::{self, Endpoint}};
+use super::{Paginated, CivitaiClient, endpoints::{self, Endpoint}};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Creator {
     ///
@@ -22,7 +22,26 @@ pub struct Creator {
     pub link: String,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    ///
+    /// The name of the tag
+    ///
+    pub name: String,
+
+    ///
+    /// The amount of models linked to this tag
+    ///
+    pub model_count: Option<usize>,
+
+    ///
+    /// Url to get all models with this tag
+    ///
+    pub link: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
 pub enum Nsfw {
     #[default]
     #[serde(rename = "None")]
@@ -49,7 +68,7 @@ impl ToString for Nsfw {
         .to_string()
     }
 }
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
 pub enum Period {
     AllTime,
     Year,
@@ -73,52 +92,51 @@ impl ToString for Period {
 }
 
 pub mod model {
-    use std::default;
+    use std::collections::HashMap;
 
     use chrono::{DateTime, Utc};
     use futures::Future;
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
+    use serde_aux::field_attributes::deserialize_option_number_from_string;
 
     use crate::api::{
         endpoints::{self, Endpoint},
-        Paginated,
+        CivitaiClient, Paginated,
     };
 
-    use super::{super::utils::datetime, Nsfw};
-
-    #[derive(Debug, Deserialize, Clone, Copy)]
-    pub enum Type {
-        #[serde(rename = "Checkpoint")]
-        Checkpoint,
-
-        #[serde(rename = "TextualInversion")]
-        TextualInversion,
-
-        #[serde(rename = "Hypernetwork")]
-        Hypernetwork,
-
-        #[serde(rename = "AestheticGradient")]
-        AestheticGradient,
-
-        #[serde(rename = "LORA")]
-        Lora,
-
-        #[serde(rename = "Controlnet")]
-        Controlnet,
+    use super::{
+        super::utils::{datetime, open_enum},
+        Nsfw,
+    };
 
-        #[serde(rename = "Poses")]
-        Poses,
+    open_enum! {
+        pub enum Type {
+            Checkpoint = "Checkpoint",
+            TextualInversion = "TextualInversion",
+            Hypernetwork = "Hypernetwork",
+            AestheticGradient = "AestheticGradient",
+            Lora = "LORA",
+            LoCon = "LoCon",
+            DoRA = "DoRA",
+            Controlnet = "Controlnet",
+            Upscaler = "Upscaler",
+            MotionModule = "MotionModule",
+            Vae = "VAE",
+            Poses = "Poses",
+            Wildcards = "Wildcards",
+            Workflows = "Workflows",
+            Other = "Other",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone, Copy)]
-    pub enum Mode {
-        #[serde(rename = "Archived")]
-        Archived,
-        #[serde(rename = "TakenDown")]
-        TakenDown,
+    open_enum! {
+        pub enum Mode {
+            Archived = "Archived",
+            TakenDown = "TakenDown",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Creator {
         ///
@@ -132,7 +150,7 @@ pub mod model {
         pub image: Option<String>,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Stats {
         ///
@@ -161,7 +179,34 @@ pub mod model {
         pub rating: f64,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ParentModel {
+        ///
+        /// The name of the model
+        ///
+        pub name: String,
+
+        ///
+        /// The model type
+        ///
+        #[serde(rename = "type")]
+        pub _type: Type,
+
+        ///
+        /// If the model is NSFW
+        ///
+        #[serde(default)]
+        pub nsfw: bool,
+
+        ///
+        /// If the model depicts a person of interest
+        ///
+        #[serde(default)]
+        pub poi: bool,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Version {
         ///
@@ -169,6 +214,17 @@ pub mod model {
         ///
         pub id: usize,
 
+        ///
+        /// The identifier of the model this version belongs to
+        ///
+        pub model_id: Option<usize>,
+
+        ///
+        /// Summary of the parent model
+        /// (only when the version is fetched on its own)
+        ///
+        pub model: Option<ParentModel>,
+
         ///
         /// The name of the model version
         ///
@@ -182,7 +238,10 @@ pub mod model {
         ///
         /// The date in which the version was created
         ///
-        #[serde(deserialize_with = "datetime::deserialize_option")]
+        #[serde(
+            deserialize_with = "datetime::deserialize_option",
+            serialize_with = "datetime::serialize_option"
+        )]
         pub created_at: Option<DateTime<Utc>>,
 
         ///
@@ -204,67 +263,64 @@ pub mod model {
     impl Version {
         pub fn get_images(
             &self,
-        ) -> impl Future<Output = anyhow::Result<Paginated<Image>>> + 'static {
-            endpoints::images::images::get(endpoints::images::Params {
-                model_version_id: self.id.into(),
-                ..Default::default()
-            })
+            client: &CivitaiClient,
+        ) -> impl Future<Output = crate::api::Result<Paginated<Image>>> + 'static {
+            endpoints::images::images::get(
+                client,
+                endpoints::images::Params {
+                    model_version_id: self.id.into(),
+                    ..Default::default()
+                },
+            )
         }
     }
 
-    #[derive(Debug, Deserialize, Clone, Copy)]
-    pub enum ScanResult {
-        #[serde(rename = "Pending")]
-        Pending,
-
-        #[serde(rename = "Success")]
-        Success,
-
-        #[serde(rename = "Danger")]
-        Danger,
-
-        #[serde(rename = "Error")]
-        Error,
+    open_enum! {
+        pub enum ScanResult {
+            Pending = "Pending",
+            Success = "Success",
+            Danger = "Danger",
+            Error = "Error",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone, Copy)]
-    pub enum FloatingPoint {
-        #[serde(rename = "fp16")]
-        Fp16,
-
-        #[serde(rename = "fp32")]
-        Fp32,
+    open_enum! {
+        pub enum FloatingPoint {
+            Fp8 = "fp8",
+            Fp16 = "fp16",
+            Bf16 = "bf16",
+            Fp32 = "fp32",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone, Copy)]
-    pub enum Size {
-        #[serde(rename = "full")]
-        Full,
-
-        #[serde(rename = "pruned")]
-        Pruned,
+    open_enum! {
+        pub enum Size {
+            Full = "full",
+            Pruned = "pruned",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone, Default)]
-    pub enum Format {
-        #[serde(rename = "SafeTensor")]
-        SafeTensor,
-
-        #[serde(rename = "PickleTensor")]
-        PickleTensor,
-
-        #[serde(rename = "Other")]
-        #[default]
-        Other,
+    open_enum! {
+        #[derive(Default)]
+        pub enum Format {
+            SafeTensor = "SafeTensor",
+            PickleTensor = "PickleTensor",
+            Diffusers = "Diffusers",
+            GGUF = "GGUF",
+            CoreML = "Core ML",
+            Onnx = "ONNX",
+            #[default]
+            Other = "Other",
+        }
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct FileMetadata {
         ///
         /// The specified floating point for the file
         ///
-        pub fp: FloatingPoint,
+        pub fp: Option<FloatingPoint>,
 
         ///
         /// The specified model size for the file
@@ -277,25 +333,64 @@ pub mod model {
         pub format: Option<Format>,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum HashKind {
+        AutoV1,
+        AutoV2,
+        Sha256,
+        Crc32,
+        Blake3,
+    }
+
+    ///
+    /// Hashes as hex strings (upper case, as the API sends them).
+    ///
+    #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
     pub struct Hashes {
+        ///
+        /// SHA256 of the 64KiB from 1MiB into the file, first 8 digits (A1111's old model hash)
+        ///
         #[serde(rename = "AutoV1")]
-        auto_v1: Option<String>,
+        pub auto_v1: Option<String>,
 
+        ///
+        /// SHA256 of the whole file, first 10 digits
+        ///
         #[serde(rename = "AutoV2")]
-        auto_v2: Option<String>,
+        pub auto_v2: Option<String>,
 
         #[serde(rename = "SHA256")]
-        sha256: Option<String>,
+        pub sha256: Option<String>,
 
         #[serde(rename = "CRC32")]
-        crc32: Option<String>,
+        pub crc32: Option<String>,
 
         #[serde(rename = "BLAKE3")]
-        blake3: Option<String>,
+        pub blake3: Option<String>,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    impl Hashes {
+        ///
+        /// All known hashes, strongest first.
+        ///
+        pub fn iter(&self) -> impl Iterator<Item = (HashKind, &str)> {
+            [
+                (HashKind::Sha256, &self.sha256),
+                (HashKind::Blake3, &self.blake3),
+                (HashKind::AutoV2, &self.auto_v2),
+                (HashKind::AutoV1, &self.auto_v1),
+                (HashKind::Crc32, &self.crc32),
+            ]
+            .into_iter()
+            .filter_map(|(kind, hash)| hash.as_deref().map(|h| (kind, h)))
+        }
+
+        pub fn get(&self, kind: HashKind) -> Option<&str> {
+            self.iter().find(|(k, _)| *k == kind).map(|(_, h)| h)
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct File {
         ///
@@ -306,7 +401,7 @@ pub mod model {
         ///
         /// The size of the model file
         ///
-        #[serde(rename = "sizeKb")]
+        #[serde(rename = "sizeKB")]
         pub size_kb: Option<f64>,
 
         ///
@@ -314,6 +409,11 @@ pub mod model {
         ///
         pub name: String,
 
+        ///
+        /// Precision, size and format of the file
+        ///
+        pub metadata: Option<FileMetadata>,
+
         ///
         /// Status of the pickle scan
         ///
@@ -337,7 +437,10 @@ pub mod model {
         ///
         /// The date in which the file was scanned
         ///
-        #[serde(deserialize_with = "datetime::deserialize_option")]
+        #[serde(
+            deserialize_with = "datetime::deserialize_option",
+            serialize_with = "datetime::serialize_option"
+        )]
         pub scanned_at: Option<DateTime<Utc>>,
 
         ///
@@ -356,7 +459,7 @@ pub mod model {
         pub download_url: String,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct ImageStats {
         cry_count: Option<usize>,
@@ -366,7 +469,7 @@ pub mod model {
         comment_count: Option<usize>,
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Image {
         ///
@@ -391,14 +494,21 @@ pub mod model {
 
         ///
         /// The height of the image
-        ///The ID of the post the image belongs toge
+        ///
+        pub height: usize,
+
+        ///
+        /// The NSFW level of the image
         ///
         pub nsfw_level: Nsfw,
 
         ///
         /// The date the image was posted
         ///
-        #[serde(deserialize_with = "datetime::deserialize_option")]
+        #[serde(
+            deserialize_with = "datetime::deserialize_option",
+            serialize_with = "datetime::serialize_option"
+        )]
         pub created_at: Option<DateTime<Utc>>,
 
         ///
@@ -415,10 +525,156 @@ pub mod model {
         /// The username of the creator
         ///
         pub username: Option<String>,
+
+        ///
+        /// The generation parameters of the image, if shared
+        ///
+        pub meta: Option<GenerationMeta>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Resource {
+        ///
+        /// The name of the resource (model, LoRA, embedding, ...)
+        ///
+        pub name: Option<String>,
+
+        ///
+        /// The kind of resource, like `model` or `lora`
+        ///
+        #[serde(rename = "type")]
+        pub _type: Option<String>,
+
+        ///
+        /// The weight the resource was applied with
+        ///
+        #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+        pub weight: Option<f64>,
+
+        ///
+        /// The (AutoV2) hash of the resource
+        ///
+        pub hash: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GenerationMeta {
+        ///
+        /// The prompt used to generate the image
+        ///
+        pub prompt: Option<String>,
+
+        ///
+        /// The negative prompt used to generate the image
+        ///
+        pub negative_prompt: Option<String>,
+
+        ///
+        /// The sampler, like `DPM++ 2M Karras`
+        ///
+        pub sampler: Option<String>,
+
+        ///
+        /// The classifier-free guidance scale
+        ///
+        #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+        pub cfg_scale: Option<f64>,
+
+        ///
+        /// The number of sampling steps
+        ///
+        #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+        pub steps: Option<u32>,
+
+        ///
+        /// The seed of the image
+        ///
+        #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+        pub seed: Option<i64>,
+
+        ///
+        /// The size of the image, like `512x768`
+        ///
+        #[serde(rename = "Size")]
+        pub size: Option<String>,
+
+        ///
+        /// The (AutoV2) hash of the checkpoint
+        ///
+        #[serde(rename = "Model hash")]
+        pub model_hash: Option<String>,
+
+        ///
+        /// The name of the checkpoint
+        ///
+        #[serde(rename = "Model")]
+        pub model: Option<String>,
+
+        ///
+        /// The models, LoRAs, embeddings, ... used
+        ///
+        #[serde(default)]
+        pub resources: Vec<Resource>,
+
+        ///
+        /// Everything else (`Clip skip`, `Hires upscale`, ...)
+        ///
+        #[serde(flatten)]
+        pub extra: HashMap<String, serde_json::Value>,
+    }
+
+    impl GenerationMeta {
+        ///
+        /// The parameters in the format AUTOMATIC1111's web UI writes (and reads back),
+        /// so the image can be reproduced.
+        ///
+        pub fn parameters(&self) -> String {
+            let mut out = self.prompt.clone().unwrap_or_default();
+
+            if let Some(ref negative) = self.negative_prompt {
+                out.push_str(&format!("\nNegative prompt: {negative}"));
+            }
+
+            let mut extra = self
+                .extra
+                .iter()
+                .filter_map(|(k, v)| match v {
+                    serde_json::Value::String(s) => Some((k.clone(), s.clone())),
+                    serde_json::Value::Number(n) => Some((k.clone(), n.to_string())),
+                    serde_json::Value::Bool(b) => Some((k.clone(), b.to_string())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            extra.sort();
+
+            let settings = [
+                ("Steps", self.steps.map(|a| a.to_string())),
+                ("Sampler", self.sampler.clone()),
+                ("CFG scale", self.cfg_scale.map(|a| a.to_string())),
+                ("Seed", self.seed.map(|a| a.to_string())),
+                ("Size", self.size.clone()),
+                ("Model hash", self.model_hash.clone()),
+                ("Model", self.model.clone()),
+            ]
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
+            .chain(extra)
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect::<Vec<_>>();
+
+            if !settings.is_empty() {
+                out.push('\n');
+                out.push_str(&settings.join(", "));
+            }
+
+            out
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Model {
     ///
@@ -471,12 +727,26 @@ pub struct Model {
 
 
 impl Model {
+    ///
+    /// Fetches the current state of this model, without re-running a search.
+    ///
+    pub fn refresh(
+        &self,
+        client: &CivitaiClient,
+    ) -> impl Future<Output = crate::api::Result<Model>> + 'static {
+        endpoints::model::model::get(client, self.id.into())
+    }
+
     pub fn get_images(
         &self,
-    ) -> impl Future<Output = anyhow::Result<Paginated<model::Image>>> + 'static {
-        endpoints::images::images::get(endpoints::images::Params {
-            model_version_id: self.id.into(),
-            ..Default::default()
-        })
+        client: &CivitaiClient,
+    ) -> impl Future<Output = crate::api::Result<Paginated<model::Image>>> + 'static {
+        endpoints::images::images::get(
+            client,
+            endpoints::images::Params {
+                model_id: self.id.into(),
+                ..Default::default()
+            },
+        )
     }
 }
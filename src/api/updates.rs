@@ -0,0 +1,174 @@
+//!
+//! Which installed models have newer versions on Civitai.
+//!
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::PathBuf,
+};
+
+use super::{
+    endpoints::{self, Endpoint},
+    library::Installed,
+    types::{model::Version, Model},
+    ApiError, CivitaiClient,
+};
+
+///
+/// An installed file, and the versions of its model which came after it.
+///
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub path: PathBuf,
+    pub model: Model,
+
+    ///
+    /// The version which is installed
+    ///
+    pub installed: Version,
+
+    ///
+    /// Newest first, so the first one is the latest
+    ///
+    pub newer: Vec<Version>,
+}
+
+///
+/// What checking for updates found.
+///
+#[derive(Debug, Default)]
+pub struct Report {
+    pub updates: Vec<Update>,
+
+    ///
+    /// Installed files which are up to date
+    ///
+    pub current: Vec<PathBuf>,
+
+    ///
+    /// Installed files whose model could not be fetched
+    ///
+    pub failed: Vec<(PathBuf, ApiError)>,
+}
+
+impl Update {
+    pub fn latest(&self) -> &Version {
+        &self.newer[0]
+    }
+}
+
+///
+/// The versions of `model` newer than `installed`, newest first.
+///
+/// Civitai lists versions newest first, so these are the ones before it.
+/// If the installed version is no longer listed, its creation date decides.
+///
+pub fn newer_versions<'a>(model: &'a Model, installed: &Version) -> &'a [Version] {
+    let versions = &model.versions;
+
+    match versions.iter().position(|v| v.id == installed.id) {
+        Some(i) => &versions[..i],
+        None => {
+            let Some(at) = installed.created_at else {
+                return &[];
+            };
+
+            let i = versions
+                .iter()
+                .position(|v| v.created_at.is_some_and(|c| c <= at))
+                .unwrap_or(versions.len());
+
+            &versions[..i]
+        }
+    }
+}
+
+///
+/// Fetches the model of each installed file (once per model), to compare versions.
+///
+pub async fn check(client: &CivitaiClient, installed: &[Installed]) -> Report {
+    let mut models = HashMap::<usize, Model>::new();
+    let mut report = Report::default();
+
+    for file in installed {
+        let Some(id) = file.version.model_id else {
+            report.current.push(file.path.clone());
+            continue;
+        };
+
+        if let Entry::Vacant(slot) = models.entry(id) {
+            match endpoints::model::model::get(client, id.into()).await {
+                Ok(model) => {
+                    slot.insert(model);
+                }
+                Err(err) => {
+                    report.failed.push((file.path.clone(), err));
+                    continue;
+                }
+            }
+        }
+
+        let model = &models[&id];
+        let newer = newer_versions(model, &file.version);
+
+        if newer.is_empty() {
+            report.current.push(file.path.clone());
+            continue;
+        }
+
+        report.updates.push(Update {
+            path: file.path.clone(),
+            model: model.clone(),
+            installed: file.version.clone(),
+            newer: newer.to_vec(),
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::api::{
+        endpoints::{model::model, Endpoint},
+        fixtures,
+        library::Installed,
+    };
+
+    use super::check;
+
+    #[tokio::test]
+    async fn test_check() -> anyhow::Result<()> {
+        let client = fixtures::client();
+        let versions = model::get(&client, 1001.into()).await?.versions;
+
+        let installed = |name: &str, i: usize| Installed {
+            path: PathBuf::from(name),
+            sha256: String::new(),
+            version: versions[i].clone(),
+        };
+
+        let report = check(
+            &client,
+            &[installed("old.safetensors", 1), installed("new.safetensors", 0)],
+        )
+        .await;
+
+        assert!(report.failed.is_empty());
+        assert_eq!(report.current, [PathBuf::from("new.safetensors")]);
+
+        assert_eq!(report.updates.len(), 1);
+        let update = &report.updates[0];
+        assert_eq!(update.path, PathBuf::from("old.safetensors"));
+        assert_eq!(update.installed.name, "v1.0");
+        assert_eq!(update.latest().name, "v2.0");
+        assert_eq!(
+            update.latest().description.as_deref(),
+            Some("<p>Changes in v2.0</p>")
+        );
+
+        Ok(())
+    }
+}
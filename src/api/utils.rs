@@ -1,6 +1,17 @@
 pub mod datetime {
-    use chrono::{DateTime, Utc};
-    use serde::{Deserializer, Deserialize};
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserializer, Deserialize, Serializer};
+
+    pub fn serialize<S: Serializer>(dt: &DateTime<Utc>, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&dt.to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+
+    pub fn serialize_option<S: Serializer>(dt: &Option<DateTime<Utc>>, ser: S) -> Result<S::Ok, S::Error> {
+        match dt {
+            Some(dt) => ser.serialize_some(&dt.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            None => ser.serialize_none(),
+        }
+    }
 
     pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<DateTime<Utc>, D::Error> {
         let st = String::deserialize(de)?;
@@ -21,4 +32,180 @@ pub mod datetime {
             Ok(None)
         }
     }
-}
\ No newline at end of file
+}
+
+pub mod cursor {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    ///
+    /// Cursors are sometimes numbers, sometimes strings (like `"1234|5678"`).
+    ///
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(de: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<Raw>::deserialize(de)?.map(|raw| match raw {
+            Raw::String(s) => s,
+            Raw::Number(n) => n.to_string(),
+        }))
+    }
+}
+///
+/// A string enum which keeps values it does not know about (as `Unknown`),
+/// instead of failing the whole response.
+///
+/// ```ignore
+/// open_enum! {
+///     pub enum Size {
+///         Full = "full",
+///         Pruned = "pruned",
+///     }
+/// }
+/// ```
+///
+/// Values are matched case-insensitively. Known variants are written back
+/// in their canonical form (`"PRUNED"` comes back as `"pruned"`),
+/// only `Unknown` values are written back exactly as they came.
+///
+/// Holding a `String` for `Unknown`, these enums are `Clone` but not `Copy`.
+///
+macro_rules! open_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident = $value:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant,
+            )*
+
+            ///
+            /// A value the API sent which we do not know about (yet)
+            ///
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $( Self::$variant => $value, )*
+                    Self::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                $(
+                    if value.eq_ignore_ascii_case($value) {
+                        return Self::$variant;
+                    }
+                )*
+
+                Self::Unknown(value.to_string())
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+                ser.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+                String::deserialize(de).map(|value| Self::from(value.as_str()))
+            }
+        }
+    };
+}
+
+pub(crate) use open_enum;
+
+///
+/// The text of an HTML snippet (like a description), one paragraph or list item per line.
+///
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let tag = rest[start + 1..start + end].trim_start_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or("");
+        if matches!(name, "p" | "br" | "br/" | "li" | "div") || name.starts_with('h') {
+            text.push('\n');
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    open_enum! {
+        pub enum Size {
+            Full = "full",
+            Pruned = "pruned",
+        }
+    }
+
+    #[test]
+    fn test_open_enum() -> anyhow::Result<()> {
+        assert_eq!(serde_json::from_str::<Size>(r#""full""#)?, Size::Full);
+        assert_eq!(serde_json::from_str::<Size>(r#""Pruned""#)?, Size::Pruned);
+
+        let odd = serde_json::from_str::<Size>(r#""tiny""#)?;
+        assert_eq!(odd, Size::Unknown("tiny".to_string()));
+        assert_eq!(serde_json::to_string(&odd)?, r#""tiny""#);
+        assert_eq!(Size::Full.to_string(), "full");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_html() {
+        assert_eq!(
+            super::strip_html("<p>Fixed <b>hands</b> &amp; feet</p><ul><li>One</li><li>Two</li></ul>"),
+            "Fixed hands & feet\nOne\nTwo"
+        );
+        assert_eq!(super::strip_html("plain"), "plain");
+    }
+}
@@ -0,0 +1,172 @@
+//!
+//! Hashing local files the way Civitai does, to check them against [Hashes].
+//!
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use super::types::model::{HashKind, Hashes};
+
+///
+/// Where, and how much of, the file AutoV1 hashes.
+///
+const AUTO_V1_OFFSET: u64 = 0x100000;
+const AUTO_V1_LEN: u64 = 0x10000;
+
+const BUF_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub kind: HashKind,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is {}, expected {}",
+            self.kind, self.actual, self.expected
+        )
+    }
+}
+
+///
+/// The `kinds` of hashes of the file at `path`, in one pass over it
+/// (AutoV1 aside, which only reads its own 64KiB).
+///
+/// This reads the whole file: call it from a blocking task.
+///
+pub fn compute(path: &Path, kinds: &[HashKind]) -> io::Result<Hashes> {
+    let wants = |kind| kinds.contains(&kind);
+    let mut file = fs::File::open(path)?;
+
+    let mut sha256 = (wants(HashKind::Sha256) || wants(HashKind::AutoV2)).then(Sha256::new);
+    let mut blake3 = wants(HashKind::Blake3).then(blake3::Hasher::new);
+    let mut crc32 = wants(HashKind::Crc32).then(crc32fast::Hasher::new);
+
+    if sha256.is_some() || blake3.is_some() || crc32.is_some() {
+        let mut buf = vec![0; BUF_LEN];
+
+        loop {
+            let len = match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            let chunk = &buf[..len];
+            sha256.iter_mut().for_each(|h| h.update(chunk));
+            blake3.iter_mut().for_each(|h| {
+                h.update(chunk);
+            });
+            crc32.iter_mut().for_each(|h| h.update(chunk));
+        }
+    }
+
+    let sha256 = sha256.map(|h| hex(&h.finalize()));
+
+    let auto_v1 = match wants(HashKind::AutoV1) {
+        true => {
+            let mut chunk = vec![];
+            file.seek(SeekFrom::Start(AUTO_V1_OFFSET))?;
+            file.take(AUTO_V1_LEN).read_to_end(&mut chunk)?;
+
+            Some(hex(&Sha256::digest(&chunk))[..8].to_string())
+        }
+        false => None,
+    };
+
+    Ok(Hashes {
+        auto_v1,
+        auto_v2: sha256
+            .as_ref()
+            .filter(|_| wants(HashKind::AutoV2))
+            .map(|h| h[..10].to_string()),
+        sha256: sha256.filter(|_| wants(HashKind::Sha256)),
+        crc32: crc32.map(|h| format!("{:08X}", h.finalize())),
+        blake3: blake3.map(|h| hex(h.finalize().as_bytes())),
+    })
+}
+
+///
+/// Checks the file at `path` against every hash in `expected`,
+/// giving back those which differ (none, if it is the right file).
+///
+pub fn verify(path: &Path, expected: &Hashes) -> io::Result<Vec<Mismatch>> {
+    let kinds = expected.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+    let actual = compute(path, &kinds)?;
+
+    Ok(expected
+        .iter()
+        .filter_map(|(kind, hash)| {
+            let got = actual.get(kind).unwrap_or_default();
+
+            (!got.eq_ignore_ascii_case(hash.trim())).then(|| Mismatch {
+                kind,
+                expected: hash.to_string(),
+                actual: got.to_string(),
+            })
+        })
+        .collect())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::types::model::{HashKind, Hashes};
+
+    use super::{compute, verify};
+
+    #[test]
+    fn test_compute() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("civitai-verify-{}", std::process::id()));
+        std::fs::write(&path, "abc")?;
+
+        let all = [
+            HashKind::AutoV1,
+            HashKind::AutoV2,
+            HashKind::Sha256,
+            HashKind::Crc32,
+            HashKind::Blake3,
+        ];
+        let hashes = compute(&path, &all)?;
+
+        let sha256 = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert_eq!(hashes.sha256.as_deref(), Some(sha256));
+        assert_eq!(hashes.auto_v2.as_deref(), Some(&sha256[..10]));
+        assert_eq!(hashes.crc32.as_deref(), Some("352441C2"));
+        assert_eq!(
+            hashes.blake3.as_deref(),
+            Some("6437B3AC38465133FFB63B75273A8DB548C558465D79DB03FD359C6CD5BD9D85")
+        );
+        // Too short to reach the AutoV1 window, so that hashes nothing.
+        assert_eq!(hashes.auto_v1.as_deref(), Some("E3B0C442"));
+
+        let only = compute(&path, &[HashKind::Crc32])?;
+        assert!(only.sha256.is_none() && only.crc32.is_some());
+
+        let expected = Hashes {
+            sha256: Some(sha256.to_lowercase()),
+            crc32: Some("00000000".to_string()),
+            ..Default::default()
+        };
+        let mismatches = verify(&path, &expected)?;
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].kind, HashKind::Crc32);
+        assert_eq!(mismatches[0].actual, "352441C2");
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -14,6 +14,7 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use lazy_static::lazy_static;
+use civitai_tui::api::CivitaiClient;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -22,13 +23,15 @@ use ratatui::{
 };
 use tokio::sync::OnceCell;
 
-use super::{navbar::NavBar, pages::{splash::Splash, search::Search}, textbox::TextBox, State};
+use super::{navbar::NavBar, pages::{splash::Splash, search::Search, tags::Tags, updates::Updates}, textbox::TextBox, State};
 
 type Term = Terminal<CrosstermBackend<Stdout>>;
 
 pub enum Pages {
     Splash(Splash),
     Search(Search),
+    Tags(Tags),
+    Updates(Updates),
 }
 
 impl Pages {
@@ -36,6 +39,8 @@ impl Pages {
         match self {
             Pages::Splash(ref s) => frame.render_widget(s.widget(), area),
             Pages::Search(ref s) => frame.render_widget(s.widget(), area),
+            Pages::Tags(ref s) => frame.render_widget(s.widget(), area),
+            Pages::Updates(ref s) => frame.render_widget(s.widget(), area),
         }
     }
 
@@ -43,6 +48,8 @@ impl Pages {
         match self {
             Pages::Splash(ref mut s) => s.input(event),
             Pages::Search(ref mut s) => s.input(event),
+            Pages::Tags(ref mut s) => s.input(event),
+            Pages::Updates(ref mut s) => s.input(event),
         }
     }
 
@@ -50,6 +57,8 @@ impl Pages {
         match self {
             Pages::Splash(ref mut s) => s.focus(),
             Pages::Search(ref mut s) => s.focus(),
+            Pages::Tags(ref mut s) => s.focus(),
+            Pages::Updates(ref mut s) => s.focus(),
         }
     }
 
@@ -57,6 +66,8 @@ impl Pages {
         match self {
             Pages::Splash(ref mut s) => s.unfocus(),
             Pages::Search(ref mut s) => s.unfocus(),
+            Pages::Tags(ref mut s) => s.unfocus(),
+            Pages::Updates(ref mut s) => s.unfocus(),
         }
     }
 }
@@ -72,6 +83,8 @@ pub struct App {
     esc_navbar: NavBar,
 
     esc: bool,
+
+    client: CivitaiClient,
 }
 
 lazy_static! {
@@ -93,6 +106,7 @@ impl App {
                 page: Pages::Splash(Splash),
                 esc_navbar: NavBar::default(),
                 esc: false,
+                client: CivitaiClient::from_env()?,
             });
         }
 
@@ -126,9 +140,28 @@ impl App {
                         },
                         KeyCode::Char('q') if self.esc => break,
                         KeyCode::Char('s') if self.esc => {
-                            self.page = Pages::Search(Search::new());
+                            self.page = Pages::Search(Search::new(self.client.clone()));
+                            continue;
+                        },
+                        KeyCode::Char('t') if self.esc => {
+                            self.page = Pages::Tags(Tags::new(&self.client));
                             continue;
                         },
+                        KeyCode::Char('u') if self.esc => {
+                            self.page = Pages::Updates(Updates::new(&self.client));
+                            continue;
+                        },
+                        // Selecting a tag searches the models with it.
+                        KeyCode::Enter if !self.esc => {
+                            if let Pages::Tags(ref tags) = self.page {
+                                if let Some(tag) = tags.selected() {
+                                    let mut search = Search::with_tag(self.client.clone(), tag);
+                                    search.focus();
+                                    self.page = Pages::Search(search);
+                                }
+                                continue;
+                            }
+                        },
                         _ => {}
                     }
                 }
@@ -144,4 +144,6 @@ mod icons {
     pub const SEARCH: Icon = Icon('\u{ea6d}');
     pub const HASH: Icon = Icon('\u{f4df}');
     pub const IMAGE: Icon = Icon('\u{f02e9}');
+    pub const TAG: Icon = Icon('\u{f04f9}');
+    pub const UPDATE: Icon = Icon('\u{f06b0}');
 }
@@ -60,8 +60,10 @@ impl<'a> Widget for NavBarW<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let state = self.0.downcast_ref::<NavBar>().unwrap();
 
-        const ELEMENTS: [ShortcutGuide; 3] = [
+        const ELEMENTS: [ShortcutGuide; 5] = [
             ShortcutGuide(icons::SEARCH, 'S', "Search"),
+            ShortcutGuide(icons::TAG, 'T', "Tags"),
+            ShortcutGuide(icons::UPDATE, 'U', "Updates"),
             ShortcutGuide(icons::HELP, 'H', "Help"),
             ShortcutGuide(icons::QUIT, 'Q', "Quit"),
         ];
@@ -82,6 +84,8 @@ impl<'a> Widget for NavBarW<'a> {
         let els = [
             Constraint::Percentage(100),
             Constraint::Min(11),
+            Constraint::Min(9),
+            Constraint::Min(12),
             Constraint::Min(10),
             Constraint::Min(10),
         ];
@@ -100,6 +104,8 @@ impl<'a> Widget for NavBarW<'a> {
         ELEMENTS[0].render(layout[1], buf);
         ELEMENTS[1].render(layout[2], buf);
         ELEMENTS[2].render(layout[3], buf);
+        ELEMENTS[3].render(layout[4], buf);
+        ELEMENTS[4].render(layout[5], buf);
     }
 }
 
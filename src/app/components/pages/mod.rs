@@ -1,2 +1,4 @@
 pub mod splash;
 pub mod search;
+pub mod tags;
+pub mod updates;
@@ -1,3 +1,4 @@
+pub mod files;
 pub mod model;
 pub mod modellist;
 pub mod meta;
@@ -5,7 +6,8 @@ pub mod img;
 
 use std::any::Any;
 
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use civitai_tui::api::CivitaiClient;
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     prelude::{Buffer, Rect},
@@ -19,7 +21,7 @@ use crate::app::components::{
     Component, State,
 };
 
-use self::modellist::ModelList;
+use self::modellist::{ModelList, MAX_MODELS};
 
 pub struct Search {
     focus: bool,
@@ -28,18 +30,26 @@ pub struct Search {
 }
 
 impl Search {
-    pub fn new() -> Self {
+    pub fn new(client: CivitaiClient) -> Self {
         Self {
             focus: false,
             query: TextBox::new(format!("{} ", icons::SEARCH)),
-            results: ModelList::default(),
+            results: ModelList::new(client),
         }
     }
-}
 
-impl Default for Search {
-    fn default() -> Self {
-        Self::new()
+    ///
+    /// Searches the models with `tag`, right away.
+    ///
+    pub fn with_tag(client: CivitaiClient, tag: String) -> Self {
+        let mut results = ModelList::new(client);
+        results.set_tag(tag.clone());
+
+        Self {
+            focus: false,
+            query: TextBox::new(format!("{} {} {tag} ", icons::SEARCH, icons::TAG)),
+            results,
+        }
     }
 }
 
@@ -49,9 +59,33 @@ impl State for Search {
     }
 
     fn input(&mut self, event: crossterm::event::Event) {
+        if let Event::Key(KeyEvent {
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            code,
+            ..
+        }) = event
+        {
+            let by = match code {
+                KeyCode::Up => Some(-1),
+                KeyCode::Down => Some(1),
+                KeyCode::PageUp => Some(-(MAX_MODELS as isize)),
+                KeyCode::PageDown => Some(MAX_MODELS as isize),
+                _ => None,
+            };
+
+            if let Some(by) = by {
+                self.results.scroll(by);
+                return;
+            }
+        }
+
         self.query.input(event.clone());
 
-        if let Event::Key(_) = event {
+        if let Event::Key(KeyEvent {
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            ..
+        }) = event
+        {
             self.results.query_update(self.query.value());
         }
     }
@@ -0,0 +1,59 @@
+//!
+//! A version's files, with what Civitai's scans made of them
+//!
+
+use civitai_tui::api::{
+    safety::{self, Policy, Unsafe},
+    types::model::{File, ScanResult},
+};
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget, Wrap},
+};
+
+///
+/// One line per file: its name, each scan's result (and message, unless it passed),
+/// and whether the default [Policy] would download it.
+///
+pub struct FilesW<'a>(pub(crate) &'a [File]);
+
+impl<'a> Widget for FilesW<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.0.iter().map(file_line).collect::<Vec<_>>();
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
+fn file_line(file: &File) -> Line<'_> {
+    let mut spans = vec![Span::raw(file.name.as_str()), Span::raw("  ")];
+
+    for (scan, result, message) in safety::scans(file) {
+        let style = match result {
+            Some(ScanResult::Success) => Style::new().green(),
+            Some(ScanResult::Pending) | None => Style::new().yellow(),
+            Some(_) => Style::new().light_red(),
+        };
+
+        let result = result.map_or("Not scanned", ScanResult::as_str);
+        spans.push(Span::styled(format!("{scan}: {result} "), style));
+
+        if let Some(message) = message.filter(|_| result != ScanResult::Success.as_str()) {
+            spans.push(Span::styled(format!("({message}) "), Style::new().dim()));
+        }
+    }
+
+    match Policy::default().check(file) {
+        Ok(()) => {}
+        Err(Unsafe::Pickle(_)) => {
+            spans.push(Span::styled("Pickle: needs an override", Style::new().yellow()))
+        }
+        Err(_) => spans.push(Span::styled("Blocked", Style::new().light_red().bold())),
+    }
+
+    Line::from(spans)
+}
@@ -2,7 +2,6 @@ use std::{
     any::Any,
     borrow::Borrow,
     cell::RefCell,
-    fs,
     ops::{Deref, DerefMut},
 };
 
@@ -12,23 +11,23 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     prelude::{Buffer, Rect},
     style::{Style, Stylize},
-    widgets::{Block, BorderType, Borders, Paragraph, Widget, Wrap, StatefulWidget},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Paragraph, StatefulWidget, Widget, Wrap,
+    },
 };
+use civitai_tui::api::{types::model::GenerationMeta, CivitaiClient};
 use ratatui_image::{picker::Picker, protocol::ResizeProtocol, Resize, ResizeImage};
 
 use crate::app::components::{animations::loading::Wave, icons, PolledFuture, State};
 
 struct ConsoleImg(Box<dyn ResizeProtocol>);
 
-async fn get_image(url: String) -> anyhow::Result<ConsoleImg> {
-    let url = url.to_string();
-
-    let r = reqwest::get(&url).await?;
-    let b = r.bytes().await?;
+async fn get_image(client: CivitaiClient, url: String) -> anyhow::Result<ConsoleImg> {
+    let b = client.fetch_bytes(&url).await?;
     let fname = url.split('/').last().unwrap();
-    fs::write(format!("test_{}", fname), b.clone()).unwrap();
     let ext = fname.split('.').last().unwrap();
-    let reader = std::io::Cursor::new(b.to_vec());
+    let reader = std::io::Cursor::new(b);
     let img = image::io::Reader::with_format(
         reader,
         image::ImageFormat::from_extension(ext)
@@ -45,13 +44,15 @@ async fn get_image(url: String) -> anyhow::Result<ConsoleImg> {
 pub struct Img {
     contents: PolledFuture<anyhow::Result<ConsoleImg>>,
     loading: Wave<5>,
+    meta: Option<GenerationMeta>,
 }
 
 impl Img {
-    pub fn new(url: impl ToString) -> Self {
+    pub fn new(url: impl ToString, meta: Option<GenerationMeta>, client: &CivitaiClient) -> Self {
         Self {
-            contents: PolledFuture::wrap(get_image(url.to_string())),
+            contents: PolledFuture::wrap(get_image(client.clone(), url.to_string())),
             loading: Default::default(),
+            meta,
         }
     }
 }
@@ -67,10 +68,20 @@ pub struct ImgW<'a>(&'a dyn Any);
 impl<'a> Widget for ImgW<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let s = self.0.downcast_ref::<Img>().unwrap();
-        let block = Block::new()
+        let mut block = Block::new()
             .borders(Borders::all())
             .border_type(BorderType::Rounded)
             .title(format!(" {} Image", icons::IMAGE));
+
+        // Enough to find the image again, and reproduce it.
+        if let Some(ref meta) = s.meta {
+            let seed = meta.seed.map(|s| format!(" seed {s} ")).unwrap_or_default();
+            let prompt = meta.prompt.as_deref().unwrap_or_default();
+
+            block = block
+                .title(Title::from(seed).alignment(Alignment::Right))
+                .title(Title::from(format!(" {prompt} ")).position(Position::Bottom));
+        }
         let inner = block.inner(area);
         block.render(area, buf);
 
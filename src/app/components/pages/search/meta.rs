@@ -3,21 +3,32 @@
 //! 
 
 use civitai_tui::api;
-use ratatui::{widgets::{Widget, Paragraph, Block, Borders, BorderType}, prelude::{Rect, Buffer}, layout::{Layout, Constraint, Alignment}, style::{Style, Stylize}};
+use ratatui::{widgets::{Widget, Paragraph, Block, Borders, BorderType}, prelude::{Rect, Buffer}, layout::{Layout, Constraint, Alignment}, style::{Style, Stylize}, text::{Line, Span}};
 
 use crate::app::components::icons;
 
-pub struct MetaW<'a>(pub(crate) &'a api::PaginationMeta);
+///
+/// The page's metadata, and how many of its items could not be read.
+///
+pub struct MetaW<'a>(pub(crate) &'a api::PaginationMeta, pub(crate) usize);
 
 impl<'a> Widget for MetaW<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let s = self.0;
         let total = s.total_items;
 
-        let text = format!("{}{total} results", icons::HASH);
+        let skipped = self.1;
 
-        Paragraph::new(text)
-            .style(Style::new().bold())
+        let mut text = vec![];
+        if skipped > 0 {
+            text.push(Span::styled(
+                format!("{skipped} could not be read  "),
+                Style::new().light_red(),
+            ));
+        }
+        text.push(Span::styled(format!("{}{total} results", icons::HASH), Style::new().bold()));
+
+        Paragraph::new(Line::from(text))
             .alignment(Alignment::Right)
             .block(
                 Block::new()
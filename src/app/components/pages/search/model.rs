@@ -1,6 +1,6 @@
 use std::{any::Any, iter, ops::Deref};
 
-use civitai_tui::api::{self, types::model::Mode, paginated::Paginated};
+use civitai_tui::api::{self, types::model::Mode, paginated::Paginated, CivitaiClient};
 use futures::{TryFutureExt, Future};
 use ratatui::{
     layout::{Alignment, Layout, Constraint, Direction},
@@ -10,7 +10,7 @@ use ratatui::{
 
 use crate::app::components::{State, PolledFuture};
 
-use super::img::Img;
+use super::{files::FilesW, img::Img};
 
 pub struct Model {
     data: api::types::Model,
@@ -20,24 +20,40 @@ pub struct Model {
 
 const IMAGES: usize = 3;
 
-pub async fn at_most<const N: usize>(pag: Paginated<api::types::model::Image> ) -> anyhow::Result<Vec<Img>> {
+pub async fn at_most<const N: usize>(
+    client: CivitaiClient,
+    pag: Paginated<api::types::model::Image>,
+) -> anyhow::Result<Vec<Img>> {
     let (few, _) = pag.first_few();
 
     Ok(few
         .take(N)
-        .map(|img| Img::new(&img.url))
+        .map(|img| Img::new(&img.url, img.meta.clone(), &client))
         .collect())
 }
 
 impl Model {
-    pub fn new(data: api::types::Model, first: bool) -> Self {
-        let images = PolledFuture::wrap(data.versions[0].get_images().and_then(at_most::<IMAGES>));
+    pub fn new(data: api::types::Model, first: bool, client: &CivitaiClient) -> Self {
+        let owned = client.clone();
+        let images = PolledFuture::wrap(
+            data.versions[0]
+                .get_images(client)
+                .err_into()
+                .and_then(|pag| at_most::<IMAGES>(owned, pag)),
+        );
         Self {
             data,
             images,
             first,
         }
     }
+
+    ///
+    /// Whether this is the topmost model on screen (which goes without a border).
+    ///
+    pub fn set_first(&mut self, first: bool) {
+        self.first = first;
+    }
 }
 
 impl State for Model {
@@ -79,10 +95,17 @@ impl<'a> Widget for ModelW<'a> {
             .constraints(constraints)
             .split(inner);
 
+        let info = Layout::new()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(layout[0]);
+
         let name = Paragraph::new(s.data.name.clone())
             .style(Style::new().bold());
 
-        name.render(layout[0], buf);
+        name.render(info[0], buf);
+
+        FilesW(&s.data.versions[0].files).render(info[1], buf);
 
         if !s.images.ready() {
             let vertical = Layout::new()
@@ -1,15 +1,17 @@
 use std::{
     any::Any,
     cell::RefCell,
+    collections::HashMap,
     task::{Context, Waker},
+    time::Duration,
 };
 
 use civitai_tui::api::{
     self,
     endpoints::{models::Params, Endpoint},
     paginated::Paginated,
-    send_request,
     types::model::Mode,
+    CivitaiClient, PageCache,
 };
 use futures::{
     future::BoxFuture,
@@ -34,57 +36,207 @@ use crate::app::components::{
 use super::model::Model;
 
 const WAVE_LENGTH: usize = 6;
-const MAX_MODELS: usize = 3;
+
+///
+/// How many models fit on screen at once.
+///
+pub const MAX_MODELS: usize = 3;
+
+///
+/// How long the query has to stay the same before it is searched for.
+///
+const DEBOUNCE: Duration = Duration::from_millis(300);
 
 pub struct ModelList {
+    client: CivitaiClient,
+    tag: Option<String>,
     loaders: [Wave<WAVE_LENGTH>; 2],
-    last_req: Instant,
-    list: Option<PolledFuture<anyhow::Result<(Vec<Model>, api::PaginationMeta)>>>,
+    results: RefCell<Option<Results>>,
+
+    ///
+    /// The query typed since the last search, and when to search for it
+    ///
+    typed: RefCell<Option<(String, Instant)>>,
 }
 
-async fn map_models(
-    models: Paginated<api::types::Model>,
-) -> anyhow::Result<(Vec<Model>, api::PaginationMeta)> {
-    let (pages, meta) = models.first_few();
-    Ok((
-        pages
-            .take(MAX_MODELS)
-            .cloned()
-            .enumerate()
-            .map(|(i, data)| Model::new(data, i == 0))
-            .collect(),
-        meta.clone(),
-    ))
+type Page = Paginated<api::types::Model>;
+
+///
+/// The models of one search, fetched a page at a time as they get scrolled to.
+///
+struct Results {
+    ///
+    /// What was searched for
+    ///
+    query: String,
+
+    pages: RefCell<PageCache<api::types::Model>>,
+
+    ///
+    /// The number of the page being fetched, and the fetch itself
+    ///
+    pending: RefCell<Option<(usize, PolledFuture<api::Result<Page>>)>>,
+
+    ///
+    /// Models set up for display (and loading their images), by position
+    ///
+    shown: RefCell<HashMap<usize, Model>>,
+
+    error: RefCell<Option<api::ApiError>>,
+
+    ///
+    /// Position of the topmost model on screen
+    ///
+    offset: usize,
 }
 
-impl Default for ModelList {
-    fn default() -> Self {
+impl Results {
+    fn new(query: String, pages: PageCache<api::types::Model>) -> Self {
         Self {
-            loaders: Default::default(),
-            last_req: Instant::now(),
-            list: Default::default(),
+            query,
+            pages: RefCell::new(pages),
+            pending: Default::default(),
+            shown: Default::default(),
+            error: Default::default(),
+            offset: 0,
+        }
+    }
+
+    ///
+    /// Takes in the page which just arrived, and starts on the next one missing from screen.
+    ///
+    fn poll(&self) {
+        let mut pending = self.pending.borrow_mut();
+
+        let arrived = match pending.as_ref() {
+            Some((k, fut)) if fut.ready() => Some((*k, fut.inner().take())),
+            Some(_) => return,
+            None => None,
+        };
+
+        if let Some((k, page)) = arrived {
+            pending.take();
+
+            match page {
+                Some(Ok(page)) => self.pages.borrow_mut().insert(k, page),
+                Some(Err(err)) => {
+                    self.error.replace(Some(err));
+                }
+                None => {}
+            }
+        }
+
+        if self.error.borrow().is_some() {
+            return;
+        }
+
+        let pages = self.pages.borrow();
+        let missing = match pages.page_size() {
+            None => Some(1),
+            Some(_) => (self.offset..self.offset + MAX_MODELS)
+                .filter_map(|i| pages.page_of(i))
+                .find(|&k| pages.page(k).is_none()),
+        };
+
+        if let Some((k, fut)) = missing.and_then(|k| pages.fetch(k).map(|fut| (k, fut))) {
+            pending.replace((k, PolledFuture::wrap(fut)));
+        }
+    }
+
+    ///
+    /// The number of rows: `totalItems` when the endpoint says,
+    /// otherwise what has been loaded, plus one while there is more to load
+    /// (cursor paginated responses leave `totalItems` at 0).
+    ///
+    fn total(&self) -> Option<usize> {
+        let pages = self.pages.borrow();
+
+        match pages.meta()?.total_items {
+            0 => Some(pages.loaded() + usize::from(pages.has_more())),
+            total => Some(total),
         }
     }
 }
 
 impl ModelList {
+    pub fn new(client: CivitaiClient) -> Self {
+        Self {
+            client,
+            tag: None,
+            loaders: Default::default(),
+            results: Default::default(),
+            typed: Default::default(),
+        }
+    }
+
+    ///
+    /// Searches again for `query`, once it has not changed for a moment
+    /// (see [ModelList::tick]). The same query as last time is not searched again,
+    /// so the results (and how far they were scrolled) are kept.
+    ///
     pub fn query_update(&mut self, query: &str) {
-        let now = Instant::now();
+        let searched = self.results.get_mut().as_ref().map_or("", |r| r.query.as_str());
+        let typed = self.typed.get_mut();
 
-        // Only send requests max every three mins.
-        if now.duration_since(self.last_req).as_secs() < 3 {
-            return;
+        if query == searched {
+            *typed = None;
+        } else if typed.as_ref().is_none_or(|(t, _)| t != query) {
+            *typed = Some((query.to_string(), Instant::now() + DEBOUNCE));
+        }
+    }
+
+    ///
+    /// Only show models with `tag`, and refresh the results.
+    ///
+    pub fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+        self.typed.get_mut().take();
+        self.search("");
+    }
+
+    ///
+    /// Searches for the query typed, once it is due.
+    ///
+    fn tick(&self) {
+        let due = matches!(*self.typed.borrow(), Some((_, at)) if Instant::now() >= at);
+
+        if let Some((query, _)) = due.then(|| self.typed.take()).flatten() {
+            self.search(&query);
         }
+    }
+
+    ///
+    /// Moves the list `by` models (up, if negative).
+    /// Pages already seen are kept, so scrolling back does not fetch them again.
+    ///
+    pub fn scroll(&mut self, by: isize) {
+        let Some(results) = self.results.get_mut() else {
+            return;
+        };
+
+        let last = results.total().unwrap_or_default().saturating_sub(1);
+        results.offset = results.offset.saturating_add_signed(by).min(last);
 
-        self.last_req = now;
+        // Let go of the models (and their pictures) which are far off screen.
+        let offset = results.offset;
+        results
+            .shown
+            .get_mut()
+            .retain(|&i, _| i.abs_diff(offset) < MAX_MODELS * 3);
+    }
 
-        self.list.replace(PolledFuture::wrap(
-            api::endpoints::models::models::get(Params {
-                query: query.to_string().into(),
+    fn search(&self, query: &str) {
+        let pages = api::endpoints::models::models::pages(
+            &self.client,
+            Params {
+                query: Some(query.to_string()).filter(|q| !q.is_empty()),
+                tag: self.tag.clone(),
                 ..Default::default()
-            })
-            .and_then(map_models),
-        ));
+            },
+        )
+        .lenient();
+
+        self.results.replace(Some(Results::new(query.to_string(), pages)));
     }
 }
 
@@ -100,19 +252,31 @@ impl<'a> Widget for ModelListW<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let s: &ModelList = self.0.downcast_ref().unwrap();
 
-        let list = s.list.as_ref();
+        s.tick();
 
-        if list.is_none() {
+        let results = s.results.borrow();
+        let Some(results) = results.as_ref() else {
             Paragraph::new("Type a query to get results.")
                 .alignment(Alignment::Center)
                 .render(area, buf);
 
+            return;
+        };
+
+        results.poll();
+
+        if let Some(err) = results.error.borrow().as_ref() {
+            Paragraph::new(format!("Encountered an error:\n{err}"))
+                .style(Style::new().light_red())
+                .alignment(Alignment::Center)
+                .render(area, buf);
+
             return;
         }
 
-        let list = list.unwrap();
+        let pages = results.pages.borrow();
 
-        if !list.ready() {
+        let Some(k) = pages.page_of(results.offset) else {
             // LOADING SCREEEN
             let layout = Layout::new()
                 .direction(Direction::Horizontal)
@@ -133,21 +297,6 @@ impl<'a> Widget for ModelListW<'a> {
                 .render(layout[2], buf);
 
             return;
-        }
-
-        let list = list.inner();
-        let list = list.as_ref().unwrap().as_ref();
-        // Should have list defined by now.
-
-        let (items, meta) = match list {
-            Ok(list) => list,
-            Err(err) => {
-                Paragraph::new(format!("Encountered an error:\n{err}"))
-                    .style(Style::new().light_red())
-                    .alignment(Alignment::Center);
-
-                return;
-            }
         };
 
         let layout = Layout::new()
@@ -157,23 +306,41 @@ impl<'a> Widget for ModelListW<'a> {
 
         let items_layout = Layout::new()
             .direction(Direction::Vertical)
-            .constraints(items.iter().map(|_| Constraint::Percentage(25)).collect::<Vec<_>>())
+            .constraints([Constraint::Ratio(1, MAX_MODELS as u32); MAX_MODELS])
             .split(layout[0]);
 
         // Render metadata last line.
-        MetaW(meta).render(layout[1], buf);
+        if let Some(page) = pages.page(k) {
+            MetaW(page.first_few().1, page.errors().len()).render(layout[1], buf);
+        }
+
+        let total = results.total().unwrap_or_default();
+        let mut shown = results.shown.borrow_mut();
 
-        items
-            .iter()
+        (results.offset..total.min(results.offset + MAX_MODELS))
             .zip(items_layout.iter())
-            .enumerate()
-            .for_each(|(i, (model, area))| model.widget().render(*area, buf))
+            .for_each(|(i, area)| {
+                let Some(data) = pages.get(i) else {
+                    let text = match pages.page_of(i).and_then(|k| pages.page(k)) {
+                        // Its page is here, so it must have been malformed.
+                        Some(_) => "This model could not be read.",
+                        None => "Loading…",
+                    };
+
+                    Paragraph::new(text)
+                        .alignment(Alignment::Center)
+                        .style(Style::new().dim())
+                        .render(*area, buf);
+
+                    return;
+                };
 
-        // Show list of models here.
-        // Paragraph::new("TODO: Got response from CivitAI!")
-        //     .alignment(Alignment::Center)
-        //     .render(area, buf);
+                let model = shown
+                    .entry(i)
+                    .or_insert_with(|| Model::new(data.clone(), false, &s.client));
 
-        // panic!("{:?}", list)
+                model.set_first(i == results.offset);
+                model.widget().render(*area, buf);
+            });
     }
 }
@@ -0,0 +1,215 @@
+//!
+//! Browse tags, and search the models with one of them.
+//!
+
+use std::{any::Any, cell::RefCell};
+
+use civitai_tui::api::{
+    endpoints::{
+        tags::{tags, Params},
+        Endpoint,
+    },
+    types::Tag,
+    CivitaiClient,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use futures::TryFutureExt;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders, Paragraph, Widget},
+};
+
+use crate::app::components::{
+    animations::loading::{Loading, Wave},
+    icons,
+    pages::splash::COLORS,
+    Component, PolledFuture, State,
+};
+
+const WAVE_LENGTH: usize = 6;
+const MAX_TAGS: usize = 200;
+
+pub struct Tags {
+    focus: bool,
+    selected: usize,
+    list: PolledFuture<anyhow::Result<Vec<Tag>>>,
+    loader: Wave<WAVE_LENGTH>,
+
+    ///
+    /// First visible row, kept between renders so the list only scrolls when needed.
+    ///
+    offset: RefCell<usize>,
+}
+
+impl Tags {
+    pub fn new(client: &CivitaiClient) -> Self {
+        let list = tags::get(
+            client,
+            Params {
+                limit: MAX_TAGS.into(),
+                ..Default::default()
+            },
+        )
+        .map_ok(|page| page.first_few().0.cloned().collect())
+        .err_into();
+
+        Self {
+            focus: false,
+            selected: 0,
+            list: PolledFuture::wrap(list),
+            loader: Default::default(),
+            offset: Default::default(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        if !self.list.ready() {
+            return 0;
+        }
+
+        let list = self.list.inner();
+        list.as_ref()
+            .and_then(|l| l.as_ref().ok())
+            .map(Vec::len)
+            .unwrap_or_default()
+    }
+
+    ///
+    /// The name of the highlighted tag, if the list has loaded.
+    ///
+    pub fn selected(&self) -> Option<String> {
+        if !self.list.ready() {
+            return None;
+        }
+
+        let list = self.list.inner();
+        list.as_ref()
+            .and_then(|l| l.as_ref().ok())
+            .and_then(|l| l.get(self.selected))
+            .map(|t| t.name.clone())
+    }
+}
+
+impl State for Tags {
+    fn widget(&self) -> impl Widget + '_ {
+        TagsW(self)
+    }
+
+    fn input(&mut self, event: Event) {
+        if let Event::Key(KeyEvent {
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            code,
+            ..
+        }) = event
+        {
+            match code {
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    self.selected = (self.selected + 1).min(self.len().saturating_sub(1))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn focus(&mut self) {
+        self.focus = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focus = false;
+    }
+}
+
+pub struct TagsW<'a>(&'a dyn Any);
+
+impl<'a> Widget for TagsW<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let s = self.0.downcast_ref::<Tags>().unwrap();
+
+        let block = Block::new()
+            .borders(Borders::BOTTOM)
+            .border_type(BorderType::Thick)
+            .border_style(if s.focus {
+                Style::new().fg(COLORS[1])
+            } else {
+                Style::reset()
+            })
+            .title(format!(" {} Tags ", icons::TAG));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if !s.list.ready() {
+            let layout = Layout::new()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Percentage(50),
+                    Constraint::Min(1),
+                    Constraint::Percentage(50),
+                ])
+                .split(inner);
+
+            s.loader.tick(()).render(layout[1], buf);
+            return;
+        }
+
+        let list = s.list.inner();
+
+        let loaded = match list.as_ref().unwrap() {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                Paragraph::new(format!("Encountered an error:\n{err}"))
+                    .style(Style::new().light_red())
+                    .alignment(Alignment::Center)
+                    .render(inner, buf);
+
+                return;
+            }
+        };
+
+        if loaded.is_empty() {
+            Paragraph::new("No tags found.")
+                .alignment(Alignment::Center)
+                .render(inner, buf);
+
+            return;
+        }
+
+        // Scroll just enough to keep the selection in view.
+        let height = (inner.height as usize).max(1);
+        let mut offset = s.offset.borrow_mut();
+        if s.selected < *offset {
+            *offset = s.selected;
+        } else if s.selected >= *offset + height {
+            *offset = s.selected + 1 - height;
+        }
+
+        let lines = loaded
+            .iter()
+            .enumerate()
+            .skip(*offset)
+            .take(height)
+            .map(|(i, tag)| {
+                let count = tag
+                    .model_count
+                    .map(|c| format!(" {}{c}", icons::HASH))
+                    .unwrap_or_default();
+
+                let name = if i == s.selected {
+                    Span::styled(format!("› {}", tag.name), Style::new().fg(COLORS[1]).bold())
+                } else {
+                    Span::raw(format!("  {}", tag.name))
+                };
+
+                Line::from(vec![name, Span::styled(count, Style::new().dim())])
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(Text::from(lines)).render(inner, buf);
+    }
+}
+
+impl<'a> Component<'a> for TagsW<'a> {}
@@ -0,0 +1,344 @@
+//!
+//! Installed models with newer versions, and what changed in them.
+//!
+
+use std::{any::Any, cell::RefCell, collections::HashMap, path::PathBuf};
+
+use civitai_tui::api::{
+    library::Library,
+    safetensors::Header,
+    types::model::{FileMetadata, Type},
+    updates::{self, Report},
+    utils::strip_html,
+    CivitaiClient,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::app::components::{
+    animations::loading::{Loading, Wave},
+    icons,
+    pages::splash::COLORS,
+    Component, PolledFuture, State,
+};
+
+const WAVE_LENGTH: usize = 6;
+
+///
+/// What a scan of the library, and a check of what it found, turned up.
+///
+struct Found {
+    report: Report,
+
+    ///
+    /// Files Civitai does not know
+    ///
+    unknown: usize,
+
+    ///
+    /// What the headers of the files with updates say, next to what Civitai says
+    ///
+    local: HashMap<PathBuf, Local>,
+}
+
+struct Local {
+    header: Option<(Option<Type>, FileMetadata)>,
+    api: Option<FileMetadata>,
+}
+
+pub struct Updates {
+    focus: bool,
+    selected: usize,
+    found: PolledFuture<anyhow::Result<Found>>,
+    loader: Wave<WAVE_LENGTH>,
+
+    ///
+    /// First visible row, kept between renders so the list only scrolls when needed.
+    ///
+    offset: RefCell<usize>,
+}
+
+impl Updates {
+    ///
+    /// Scans the model directories in `CIVITAI_MODEL_DIRS`, and checks what it finds.
+    ///
+    pub fn new(client: &CivitaiClient) -> Self {
+        let client = client.clone();
+        let library = Library::from_env();
+
+        let found = async move {
+            if library.dirs.is_empty() {
+                anyhow::bail!("No model directories: set CIVITAI_MODEL_DIRS");
+            }
+
+            let inventory = library.scan(&client).await;
+            let report = updates::check(&client, &inventory.installed).await;
+
+            let mut local = HashMap::new();
+            for installed in &inventory.installed {
+                if !report.updates.iter().any(|u| u.path == installed.path) {
+                    continue;
+                }
+
+                let path = installed.path.clone();
+                let header = tokio::task::spawn_blocking(move || Header::read(&path))
+                    .await?
+                    .ok()
+                    .map(|h| (h.model_type(), h.file_metadata()));
+
+                let api = installed.file().and_then(|f| f.metadata.clone());
+                local.insert(installed.path.clone(), Local { header, api });
+            }
+
+            Ok(Found {
+                report,
+                unknown: inventory.unknown.len(),
+                local,
+            })
+        };
+
+        Self {
+            focus: false,
+            selected: 0,
+            found: PolledFuture::wrap(found),
+            loader: Default::default(),
+            offset: Default::default(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        if !self.found.ready() {
+            return 0;
+        }
+
+        let found = self.found.inner();
+        found
+            .as_ref()
+            .and_then(|f| f.as_ref().ok())
+            .map(|f| f.report.updates.len())
+            .unwrap_or_default()
+    }
+}
+
+impl State for Updates {
+    fn widget(&self) -> impl Widget + '_ {
+        UpdatesW(self)
+    }
+
+    fn input(&mut self, event: Event) {
+        if let Event::Key(KeyEvent {
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            code,
+            ..
+        }) = event
+        {
+            match code {
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    self.selected = (self.selected + 1).min(self.len().saturating_sub(1))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn focus(&mut self) {
+        self.focus = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focus = false;
+    }
+}
+
+pub struct UpdatesW<'a>(&'a dyn Any);
+
+impl<'a> Widget for UpdatesW<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let s = self.0.downcast_ref::<Updates>().unwrap();
+
+        let block = Block::new()
+            .borders(Borders::BOTTOM)
+            .border_type(BorderType::Thick)
+            .border_style(if s.focus {
+                Style::new().fg(COLORS[1])
+            } else {
+                Style::reset()
+            })
+            .title(format!(" {} Updates ", icons::UPDATE));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if !s.found.ready() {
+            let layout = Layout::new()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Percentage(50),
+                    Constraint::Min(1),
+                    Constraint::Percentage(50),
+                ])
+                .split(inner);
+
+            s.loader.tick(()).render(layout[1], buf);
+            return;
+        }
+
+        let found = s.found.inner();
+
+        let Found {
+            report,
+            unknown,
+            local,
+        } = match found.as_ref().unwrap() {
+            Ok(found) => found,
+            Err(err) => {
+                Paragraph::new(format!("Encountered an error:\n{err}"))
+                    .style(Style::new().light_red())
+                    .alignment(Alignment::Center)
+                    .render(inner, buf);
+
+                return;
+            }
+        };
+
+        let rows = Layout::new()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let mut summary = vec![Span::raw(format!("{} up to date", report.current.len()))];
+        if *unknown > 0 {
+            summary.push(Span::styled(
+                format!("  {unknown} not on Civitai"),
+                Style::new().dim(),
+            ));
+        }
+        if !report.failed.is_empty() {
+            summary.push(Span::styled(
+                format!("  {} could not be checked", report.failed.len()),
+                Style::new().light_red(),
+            ));
+        }
+        Paragraph::new(Line::from(summary))
+            .alignment(Alignment::Right)
+            .render(rows[1], buf);
+
+        if report.updates.is_empty() {
+            Paragraph::new("Everything is up to date.")
+                .alignment(Alignment::Center)
+                .render(rows[0], buf);
+
+            return;
+        }
+
+        let columns = Layout::new()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[0]);
+
+        // Scroll just enough to keep the selection in view.
+        let height = (columns[0].height as usize).max(1);
+        let mut offset = s.offset.borrow_mut();
+        if s.selected < *offset {
+            *offset = s.selected;
+        } else if s.selected >= *offset + height {
+            *offset = s.selected + 1 - height;
+        }
+
+        let lines = report
+            .updates
+            .iter()
+            .enumerate()
+            .skip(*offset)
+            .take(height)
+            .map(|(i, update)| {
+                let name = if i == s.selected {
+                    Span::styled(
+                        format!("› {}", update.model.name),
+                        Style::new().fg(COLORS[1]).bold(),
+                    )
+                } else {
+                    Span::raw(format!("  {}", update.model.name))
+                };
+
+                let versions = format!(
+                    "  {} → {}",
+                    update.installed.name,
+                    update.latest().name
+                );
+
+                Line::from(vec![name, Span::styled(versions, Style::new().dim())])
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(Text::from(lines)).render(columns[0], buf);
+
+        let Some(update) = report.updates.get(s.selected) else {
+            return;
+        };
+
+        let mut changelog = vec![Line::from(Span::styled(
+            update.path.display().to_string(),
+            Style::new().dim(),
+        ))];
+        if let Some(Local { header, api }) = local.get(&update.path) {
+            let header = header
+                .as_ref()
+                .map(|(ty, meta)| {
+                    let ty = ty.as_ref().map(Type::as_str).unwrap_or("Unknown type");
+                    format!("{ty} {}", describe(meta))
+                })
+                .unwrap_or_else(|| "No header".to_string());
+            let api = api.as_ref().map(describe).unwrap_or_default();
+
+            changelog.push(Line::from(vec![
+                Span::styled("Local: ", Style::new().dim()),
+                Span::raw(header),
+                Span::styled("  Civitai: ", Style::new().dim()),
+                Span::raw(api),
+            ]));
+        }
+        for version in &update.newer {
+            changelog.push(Line::from(Span::styled(
+                version.name.clone(),
+                Style::new().bold(),
+            )));
+
+            let text = version.description.as_deref().map(strip_html);
+            changelog.extend(
+                text.as_deref()
+                    .unwrap_or("No changelog")
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+        }
+
+        Paragraph::new(changelog)
+            .wrap(Wrap { trim: true })
+            .block(Block::new().borders(Borders::LEFT))
+            .render(columns[1], buf);
+    }
+}
+
+impl<'a> Component<'a> for UpdatesW<'a> {}
+
+///
+/// Like `fp16 pruned SafeTensor`, leaving out what is not known.
+///
+fn describe(meta: &FileMetadata) -> String {
+    [
+        meta.fp.as_ref().map(|fp| fp.as_str()),
+        meta.size.as_ref().map(|size| size.as_str()),
+        meta.format.as_ref().map(|format| format.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ")
+}
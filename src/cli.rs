@@ -0,0 +1,55 @@
+//!
+//! Commands which run without the TUI.
+//!
+
+use civitai_tui::api::{library::Library, updates, utils::strip_html, CivitaiClient};
+
+///
+/// `updates`: lists the installed models (in `CIVITAI_MODEL_DIRS`) which have newer versions,
+/// with their changelogs.
+///
+pub async fn updates() -> anyhow::Result<()> {
+    let client = CivitaiClient::from_env()?;
+    let library = Library::from_env();
+
+    if library.dirs.is_empty() {
+        anyhow::bail!("No model directories: set CIVITAI_MODEL_DIRS");
+    }
+
+    let inventory = library.scan(&client).await;
+    let report = updates::check(&client, &inventory.installed).await;
+
+    for update in &report.updates {
+        println!(
+            "{} ({})\n  {} installed, {} is the latest",
+            update.model.name,
+            update.path.display(),
+            update.installed.name,
+            update.latest().name,
+        );
+
+        for version in &update.newer {
+            println!("  {}:", version.name);
+
+            let changelog = version.description.as_deref().map(strip_html);
+            for line in changelog.as_deref().unwrap_or("No changelog").lines() {
+                println!("    {line}");
+            }
+        }
+    }
+
+    if report.updates.is_empty() {
+        println!("Everything is up to date.");
+    }
+
+    for (path, err) in &report.failed {
+        eprintln!("Could not check {}: {err}", path.display());
+    }
+
+    for unknown in inventory.unknown.iter().filter(|u| u.error.is_some()) {
+        let err = unknown.error.as_ref().unwrap();
+        eprintln!("Could not identify {}: {err}", unknown.path.display());
+    }
+
+    Ok(())
+}
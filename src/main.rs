@@ -11,6 +11,7 @@ use crossterm::{
 };
 
 pub mod app;
+pub mod cli;
 
 fn destruct_terminal() {
     disable_raw_mode().unwrap();
@@ -20,6 +21,10 @@ fn destruct_terminal() {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    if std::env::args().nth(1).as_deref() == Some("updates") {
+        return cli::updates().await;
+    }
+
     std::panic::set_hook(Box::new(|panic_info| {
         destruct_terminal();
         better_panic::Settings::auto().create_panic_handler()(panic_info);